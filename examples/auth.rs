@@ -1,5 +1,4 @@
-use oauth2::TokenResponse;
-use poe_api::{PoEApi, PoEApiAccountScope, PoEApiConfigBuilder};
+use poe_api::{FileTokenStore, PoEApi, PoEApiAccountScope, PoEApiConfigBuilder};
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
@@ -18,7 +17,9 @@ async fn main() -> anyhow::Result<()> {
     .build()
     .unwrap();
 
-  let api = PoEApi::new(config).unwrap();
+  let api = PoEApi::new(config)
+    .unwrap()
+    .with_token_store(FileTokenStore::new("tokens.json"));
 
   let scopes = [
     PoEApiAccountScope::Profile,
@@ -27,13 +28,24 @@ async fn main() -> anyhow::Result<()> {
     PoEApiAccountScope::Characters,
   ];
 
-  let token = api.get_token(scopes, |url| {
-    println!("{url}");
-    Ok(())
-  }).await?;
+  let session = api
+    .login("default", scopes, |url| {
+      println!("{url}");
+      Ok(())
+    })
+    .await?;
 
-  dbg!(token.access_token().secret());
-  dbg!(token);
+  // The token itself is a secret, don't print it.
+  let token = session.token().await;
+
+  println!(
+    "Logged in until {:?} with {:?}",
+    token.expires_at, token.scopes
+  );
+
+  let profile = api.get_profile(&session).await?;
+
+  dbg!(profile);
 
   Ok(())
 }
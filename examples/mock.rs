@@ -0,0 +1,48 @@
+use poe_api::testing::{MockServer, MOCK_ACCESS_TOKEN};
+use poe_api::{Error, MemoryTokenStore, PoEApi, PoEApiAccountScope, PoEApiConfigBuilder};
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+  let server = MockServer::start()?;
+
+  let config = server
+    .configure(PoEApiConfigBuilder::default())
+    .version("0.1.0")
+    .contact_email("mock@example.com")
+    .redirect_addr("127.0.0.1:0")?
+    .build()?;
+
+  let api = PoEApi::new(config)?;
+
+  let profile = api.get_profile(MOCK_ACCESS_TOKEN).await?;
+
+  dbg!(profile);
+
+  let token = api
+    .get_token([PoEApiAccountScope::Characters], |url| {
+      server.visit(url);
+      Ok::<_, Error>(())
+    })
+    .await?;
+
+  let characters = api.list_characters(&token, None).await?;
+
+  dbg!(characters);
+
+  let api = api.with_token_store(MemoryTokenStore::new());
+
+  let session = api
+    .login("default", [PoEApiAccountScope::Profile], |url| {
+      server.visit(url);
+      Ok::<_, Error>(())
+    })
+    .await?;
+
+  api.logout(&session).await?;
+
+  let revoked = api.get_profile(&session).await;
+
+  dbg!(revoked.unwrap_err());
+
+  Ok(())
+}
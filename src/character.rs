@@ -0,0 +1,100 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{ApiResponse, Item, PoEApi, Realm, Result, TokenProvider};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Character {
+  pub id: String,
+  pub name: String,
+  pub realm: String,
+  pub class: String,
+  pub league: Option<String>,
+  pub level: u32,
+  pub experience: u64,
+  pub ruthless: Option<bool>,
+  pub expired: Option<bool>,
+  pub deleted: Option<bool>,
+  pub current: Option<bool>,
+  pub equipment: Option<Vec<Item>>,
+  pub inventory: Option<Vec<Item>>,
+  pub rucksack: Option<Vec<Item>>,
+  pub jewels: Option<Vec<Item>>,
+  pub passives: Option<CharacterPassives>,
+  pub metadata: Option<CharacterMetadata>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CharacterPassives {
+  pub hashes: Vec<u32>,
+  #[serde(default)]
+  pub hashes_ex: Vec<u32>,
+  #[serde(default)]
+  pub mastery_effects: HashMap<String, u32>,
+  #[serde(default)]
+  pub skill_overrides: HashMap<String, serde_json::Value>,
+  pub bandit_choice: Option<String>,
+  pub pantheon_major: Option<String>,
+  pub pantheon_minor: Option<String>,
+  #[serde(default)]
+  pub jewel_data: HashMap<String, JewelData>,
+  pub alternate_ascendancy: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JewelData {
+  #[serde(rename = "type")]
+  pub jewel_type: String,
+  pub radius: Option<u32>,
+  pub radius_min: Option<u32>,
+  pub radius_visual: Option<String>,
+  pub subgraph: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CharacterMetadata {
+  pub version: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct CharactersResponse {
+  characters: Vec<Character>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct CharacterResponse {
+  character: Option<Character>,
+}
+
+impl PoEApi {
+  pub async fn list_characters(
+    &self,
+    token: &(impl TokenProvider + ?Sized),
+    realm: Option<Realm>,
+  ) -> Result<ApiResponse<Vec<Character>>> {
+    let realm = self.realm_segment(realm);
+    let request = self
+      .get(&format!("/character{realm}"))?
+      .bearer_auth(token.bearer_token(self).await?);
+    let response = self.send_json::<CharactersResponse>(request).await?;
+
+    Ok(response.map(|response| response.characters))
+  }
+
+  pub async fn get_character(
+    &self,
+    token: &(impl TokenProvider + ?Sized),
+    name: &str,
+    realm: Option<Realm>,
+  ) -> Result<ApiResponse<Option<Character>>> {
+    let realm = self.realm_segment(realm);
+    let request = self
+      .get(&format!("/character{realm}/{name}"))?
+      .bearer_auth(token.bearer_token(self).await?);
+    let response = self.send_json::<CharacterResponse>(request).await?;
+
+    Ok(response.map(|response| response.character))
+  }
+}
@@ -0,0 +1,77 @@
+use std::collections::HashMap;
+
+use futures::{stream, Stream};
+use serde::{Deserialize, Serialize};
+
+use crate::{ApiResponse, Error, PoEApi, Realm, Result, TokenProvider};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CurrencyExchange {
+  /// Unix timestamp of the next hour, truncated to the hour.
+  pub next_change_id: u64,
+  pub markets: Vec<CurrencyExchangeMarket>,
+}
+
+/// Stats of a currency pair during one hour, keyed by the currency id of each side.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CurrencyExchangeMarket {
+  pub league: String,
+  /// The two currency ids of the pair separated by `|`, e.g. `chaos|divine`.
+  pub market_id: String,
+  pub volume_traded: HashMap<String, u64>,
+  pub lowest_stock: HashMap<String, u64>,
+  pub highest_stock: HashMap<String, u64>,
+  pub lowest_ratio: HashMap<String, f64>,
+  pub highest_ratio: HashMap<String, f64>,
+}
+
+impl CurrencyExchangeMarket {
+  pub fn currencies(&self) -> Option<(&str, &str)> {
+    self.market_id.split_once('|')
+  }
+}
+
+impl PoEApi {
+  /// Gets the market stats of the hour starting at `id`, or the first available hour if `None`.
+  pub async fn get_currency_exchange(
+    &self,
+    token: &(impl TokenProvider + ?Sized),
+    realm: Option<Realm>,
+    id: Option<u64>,
+  ) -> Result<ApiResponse<CurrencyExchange>> {
+    let mut endpoint = format!("/currency-exchange{}", self.realm_segment(realm));
+
+    if let Some(id) = id {
+      endpoint.push_str(&format!("/{id}"));
+    }
+
+    let request = self
+      .get(&endpoint)?
+      .bearer_auth(token.bearer_token(self).await?);
+
+    self.send_json(request).await
+  }
+
+  /// Walks forward hour by hour from `id` and ends once there is no newer hour available.
+  pub fn currency_exchange_stream<'a>(
+    &'a self,
+    token: &'a (impl TokenProvider + ?Sized),
+    realm: Option<Realm>,
+    id: Option<u64>,
+  ) -> impl Stream<Item = Result<ApiResponse<CurrencyExchange>>> + 'a {
+    stream::try_unfold(Some(id), move |id| async move {
+      let Some(id) = id else {
+        return Ok::<_, Error>(None);
+      };
+
+      let response = self.get_currency_exchange(token, realm, id).await?;
+      let next = response.next_change_id;
+      let next = match id {
+        Some(id) if next <= id => None,
+        _ => Some(Some(next)),
+      };
+
+      Ok(Some((response, next)))
+    })
+  }
+}
@@ -0,0 +1,396 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Item {
+  pub id: Option<String>,
+  pub verified: bool,
+  pub w: u32,
+  pub h: u32,
+  pub icon: String,
+  pub support: Option<bool>,
+  pub stack_size: Option<u32>,
+  pub max_stack_size: Option<u32>,
+  pub stack_size_text: Option<String>,
+  pub league: Option<String>,
+  #[serde(default)]
+  pub influences: Influences,
+  pub elder: Option<bool>,
+  pub shaper: Option<bool>,
+  pub searing: Option<bool>,
+  pub tangled: Option<bool>,
+  pub abyss_jewel: Option<bool>,
+  pub delve: Option<bool>,
+  pub fractured: Option<bool>,
+  pub synthesised: Option<bool>,
+  #[serde(default)]
+  pub sockets: Vec<ItemSocket>,
+  #[serde(default)]
+  pub socketed_items: Vec<Item>,
+  pub name: String,
+  pub type_line: String,
+  pub base_type: String,
+  pub rarity: Option<String>,
+  pub identified: bool,
+  pub item_level: Option<u32>,
+  pub ilvl: u32,
+  pub note: Option<String>,
+  #[serde(rename = "forum_note")]
+  pub forum_note: Option<String>,
+  pub locked_to_character: Option<bool>,
+  pub locked_to_account: Option<bool>,
+  pub duplicated: Option<bool>,
+  pub split: Option<bool>,
+  pub corrupted: Option<bool>,
+  pub unmodifiable: Option<bool>,
+  #[serde(default)]
+  pub properties: Vec<ItemProperty>,
+  #[serde(default)]
+  pub notable_properties: Vec<ItemProperty>,
+  #[serde(default)]
+  pub requirements: Vec<ItemProperty>,
+  #[serde(default)]
+  pub additional_properties: Vec<ItemProperty>,
+  #[serde(default)]
+  pub next_level_requirements: Vec<ItemProperty>,
+  pub talisman_tier: Option<u32>,
+  pub sec_descr_text: Option<String>,
+  #[serde(default)]
+  pub utility_mods: Vec<String>,
+  #[serde(default)]
+  pub enchant_mods: Vec<String>,
+  #[serde(default)]
+  pub scourge_mods: Vec<String>,
+  #[serde(default)]
+  pub implicit_mods: Vec<String>,
+  #[serde(default)]
+  pub explicit_mods: Vec<String>,
+  #[serde(default)]
+  pub crafted_mods: Vec<String>,
+  #[serde(default)]
+  pub fractured_mods: Vec<String>,
+  #[serde(default)]
+  pub crucible_mods: Vec<String>,
+  #[serde(default)]
+  pub cosmetic_mods: Vec<String>,
+  #[serde(default)]
+  pub veiled_mods: Vec<String>,
+  pub veiled: Option<bool>,
+  pub descr_text: Option<String>,
+  #[serde(default)]
+  pub flavour_text: Vec<String>,
+  pub is_relic: Option<bool>,
+  pub foil_variation: Option<u32>,
+  pub replica: Option<bool>,
+  pub foreseeing: Option<bool>,
+  pub incubated_item: Option<IncubatedItem>,
+  pub ruthless: Option<bool>,
+  pub frame_type: Option<FrameType>,
+  pub art_filename: Option<String>,
+  pub hybrid: Option<HybridGem>,
+  pub extended: Option<ItemExtended>,
+  pub x: Option<u32>,
+  pub y: Option<u32>,
+  pub inventory_id: Option<String>,
+  pub socket: Option<u32>,
+  pub colour: Option<SocketAttribute>,
+  /// Fields not covered above, kept so new league mechanics survive a round trip.
+  #[serde(flatten)]
+  pub extra: HashMap<String, serde_json::Value>,
+}
+
+impl Item {
+  pub fn is_influenced(&self) -> bool {
+    self.influences != Influences::default()
+  }
+
+  /// All the mod lines of this item in the order they are displayed in game.
+  pub fn mods(&self) -> impl Iterator<Item = &String> {
+    self
+      .enchant_mods
+      .iter()
+      .chain(&self.scourge_mods)
+      .chain(&self.implicit_mods)
+      .chain(&self.fractured_mods)
+      .chain(&self.explicit_mods)
+      .chain(&self.crafted_mods)
+      .chain(&self.crucible_mods)
+  }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "u32", into = "u32")]
+pub enum FrameType {
+  Normal,
+  Magic,
+  Rare,
+  Unique,
+  Gem,
+  Currency,
+  DivinationCard,
+  Quest,
+  Prophecy,
+  Foil,
+  SupporterFoil,
+  Necropolis,
+  Other(u32),
+}
+
+impl From<u32> for FrameType {
+  fn from(value: u32) -> Self {
+    match value {
+      0 => Self::Normal,
+      1 => Self::Magic,
+      2 => Self::Rare,
+      3 => Self::Unique,
+      4 => Self::Gem,
+      5 => Self::Currency,
+      6 => Self::DivinationCard,
+      7 => Self::Quest,
+      8 => Self::Prophecy,
+      9 => Self::Foil,
+      10 => Self::SupporterFoil,
+      11 => Self::Necropolis,
+      value => Self::Other(value),
+    }
+  }
+}
+
+impl From<FrameType> for u32 {
+  fn from(value: FrameType) -> Self {
+    match value {
+      FrameType::Normal => 0,
+      FrameType::Magic => 1,
+      FrameType::Rare => 2,
+      FrameType::Unique => 3,
+      FrameType::Gem => 4,
+      FrameType::Currency => 5,
+      FrameType::DivinationCard => 6,
+      FrameType::Quest => 7,
+      FrameType::Prophecy => 8,
+      FrameType::Foil => 9,
+      FrameType::SupporterFoil => 10,
+      FrameType::Necropolis => 11,
+      FrameType::Other(value) => value,
+    }
+  }
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Influences {
+  pub shaper: bool,
+  pub elder: bool,
+  pub crusader: bool,
+  pub redeemer: bool,
+  pub hunter: bool,
+  pub warlord: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ItemSocket {
+  pub group: u32,
+  pub attr: Option<SocketAttribute>,
+  pub s_colour: Option<SocketColour>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SocketAttribute {
+  #[serde(rename = "S")]
+  Strength,
+  #[serde(rename = "D")]
+  Dexterity,
+  #[serde(rename = "I")]
+  Intelligence,
+  #[serde(rename = "G")]
+  White,
+  #[serde(rename = "A")]
+  Abyss,
+  #[serde(rename = "DV")]
+  Delve,
+  #[serde(other)]
+  Unknown,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SocketColour {
+  #[serde(rename = "R")]
+  Red,
+  #[serde(rename = "G")]
+  Green,
+  #[serde(rename = "B")]
+  Blue,
+  #[serde(rename = "W")]
+  White,
+  #[serde(rename = "A")]
+  Abyss,
+  #[serde(rename = "DV")]
+  Delve,
+  #[serde(other)]
+  Unknown,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ItemProperty {
+  pub name: String,
+  #[serde(default)]
+  pub values: Vec<(String, u32)>,
+  pub display_mode: Option<DisplayMode>,
+  pub progress: Option<f64>,
+  #[serde(rename = "type")]
+  pub property_type: Option<u32>,
+  pub suffix: Option<String>,
+  pub icon: Option<String>,
+}
+
+impl ItemProperty {
+  /// Renders the property the way the game tooltip does, ignoring value colours.
+  pub fn display(&self) -> String {
+    let values = self
+      .values
+      .iter()
+      .map(|(value, _)| value.as_str())
+      .collect::<Vec<_>>();
+
+    match self.display_mode {
+      Some(DisplayMode::ValuesThenName) => format!("{} {}", values.join(", "), self.name),
+      Some(DisplayMode::InsertValues) => values
+        .iter()
+        .enumerate()
+        .fold(self.name.clone(), |name, (index, value)| {
+          name.replace(&format!("{{{index}}}"), value)
+        }),
+      _ if values.is_empty() => self.name.clone(),
+      _ => format!("{}: {}", self.name, values.join(", ")),
+    }
+  }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "u32", into = "u32")]
+pub enum DisplayMode {
+  NameThenValues,
+  ValuesThenName,
+  ProgressBar,
+  InsertValues,
+  Separator,
+  Other(u32),
+}
+
+impl From<u32> for DisplayMode {
+  fn from(value: u32) -> Self {
+    match value {
+      0 => Self::NameThenValues,
+      1 => Self::ValuesThenName,
+      2 => Self::ProgressBar,
+      3 => Self::InsertValues,
+      4 => Self::Separator,
+      value => Self::Other(value),
+    }
+  }
+}
+
+impl From<DisplayMode> for u32 {
+  fn from(value: DisplayMode) -> Self {
+    match value {
+      DisplayMode::NameThenValues => 0,
+      DisplayMode::ValuesThenName => 1,
+      DisplayMode::ProgressBar => 2,
+      DisplayMode::InsertValues => 3,
+      DisplayMode::Separator => 4,
+      DisplayMode::Other(value) => value,
+    }
+  }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IncubatedItem {
+  pub name: String,
+  pub level: u32,
+  pub progress: u32,
+  pub total: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HybridGem {
+  pub is_vaal_gem: Option<bool>,
+  pub base_type_name: String,
+  #[serde(default)]
+  pub properties: Vec<ItemProperty>,
+  #[serde(default)]
+  pub explicit_mods: Vec<String>,
+  pub sec_descr_text: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ItemExtended {
+  pub category: Option<String>,
+  #[serde(default)]
+  pub subcategories: Vec<String>,
+  pub prefixes: Option<u32>,
+  pub suffixes: Option<u32>,
+  #[serde(default)]
+  pub mods: HashMap<String, Vec<ItemModInfo>>,
+  #[serde(default)]
+  pub hashes: HashMap<String, Vec<ItemModHash>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ItemModInfo {
+  pub name: String,
+  pub tier: String,
+  pub level: Option<u32>,
+  #[serde(default)]
+  pub magnitudes: Vec<ItemModMagnitude>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ItemModMagnitude {
+  pub hash: String,
+  pub min: f64,
+  pub max: f64,
+}
+
+/// A stat hash along with the indices of the entries in [`ItemExtended::mods`] it came from.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ItemModHash(pub String, pub Option<Vec<u32>>);
+
+#[cfg(test)]
+mod tests {
+  use serde_json::json;
+
+  use super::*;
+
+  #[test]
+  fn keeps_unknown_fields() {
+    let item: Item = serde_json::from_value(json!({
+      "verified": false,
+      "w": 1,
+      "h": 1,
+      "icon": "https://web.poecdn.com/item.png",
+      "name": "",
+      "typeLine": "Chaos Orb",
+      "baseType": "Chaos Orb",
+      "identified": true,
+      "ilvl": 0,
+      "frameType": 5,
+      "memoryItem": true,
+      "mutated": { "mods": ["Foulborn"] },
+    }))
+    .unwrap();
+
+    assert_eq!(item.frame_type, Some(FrameType::Currency));
+    assert_eq!(item.extra["memoryItem"], json!(true));
+    assert_eq!(item.extra["mutated"], json!({ "mods": ["Foulborn"] }));
+    assert!(!item.extra.contains_key("typeLine"));
+
+    // Unknown fields are written back out as they came in.
+    let value = serde_json::to_value(&item).unwrap();
+    assert_eq!(value["memoryItem"], json!(true));
+  }
+}
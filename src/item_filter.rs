@@ -0,0 +1,137 @@
+use chrono::{DateTime, Utc};
+use derive_builder::Builder;
+use serde::{Deserialize, Serialize};
+
+use crate::{ApiResponse, Error, PoEApi, Realm, Result, TokenProvider};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ItemFilter {
+  pub id: String,
+  pub filter_name: String,
+  pub realm: String,
+  pub description: String,
+  pub version: String,
+  #[serde(rename = "type")]
+  pub filter_type: ItemFilterType,
+  pub public: Option<bool>,
+  /// Only present when fetching a single filter.
+  pub filter: Option<String>,
+  pub validation: Option<ItemFilterValidation>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ItemFilterType {
+  Normal,
+  Ruthless,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ItemFilterValidation {
+  pub valid: bool,
+  pub version: Option<String>,
+  pub validated: Option<DateTime<Utc>>,
+}
+
+/// Fields to set when creating or updating an item filter, unset fields are left unchanged.
+///
+/// Creating a filter requires at least `filter_name` and `filter`.
+#[derive(Debug, Clone, Default, Serialize, Builder)]
+#[builder(
+  pattern = "owned",
+  setter(into, strip_option),
+  default,
+  build_fn(error = "Error")
+)]
+pub struct ItemFilterChanges {
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub filter_name: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub realm: Option<Realm>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub description: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub version: Option<String>,
+  #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+  pub filter_type: Option<ItemFilterType>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub public: Option<bool>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub filter: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct ItemFiltersResponse {
+  filters: Vec<ItemFilter>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct ItemFilterResponse {
+  filter: ItemFilter,
+}
+
+impl PoEApi {
+  pub async fn list_item_filters(
+    &self,
+    token: &(impl TokenProvider + ?Sized),
+  ) -> Result<ApiResponse<Vec<ItemFilter>>> {
+    let request = self
+      .get("/item-filter")?
+      .bearer_auth(token.bearer_token(self).await?);
+    let response = self.send_json::<ItemFiltersResponse>(request).await?;
+
+    Ok(response.map(|response| response.filters))
+  }
+
+  pub async fn get_item_filter(
+    &self,
+    token: &(impl TokenProvider + ?Sized),
+    id: &str,
+  ) -> Result<ApiResponse<ItemFilter>> {
+    let request = self
+      .get(&format!("/item-filter/{id}"))?
+      .bearer_auth(token.bearer_token(self).await?);
+    let response = self.send_json::<ItemFilterResponse>(request).await?;
+
+    Ok(response.map(|response| response.filter))
+  }
+
+  /// Creates a new item filter, with `validate` the filter is checked against the current
+  /// version of the game.
+  pub async fn create_item_filter(
+    &self,
+    token: &(impl TokenProvider + ?Sized),
+    filter: &ItemFilterChanges,
+    validate: bool,
+  ) -> Result<ApiResponse<ItemFilter>> {
+    let filter = ItemFilterChanges {
+      realm: self.resolve_realm(filter.realm),
+      ..filter.clone()
+    };
+
+    let request = self
+      .post("/item-filter")?
+      .query(&[("validate", validate.then_some("true"))])
+      .json(&filter)
+      .bearer_auth(token.bearer_token(self).await?);
+    let response = self.send_json::<ItemFilterResponse>(request).await?;
+
+    Ok(response.map(|response| response.filter))
+  }
+
+  pub async fn update_item_filter(
+    &self,
+    token: &(impl TokenProvider + ?Sized),
+    id: &str,
+    changes: &ItemFilterChanges,
+    validate: bool,
+  ) -> Result<ApiResponse<ItemFilter>> {
+    let request = self
+      .post(&format!("/item-filter/{id}"))?
+      .query(&[("validate", validate.then_some("true"))])
+      .json(changes)
+      .bearer_auth(token.bearer_token(self).await?);
+    let response = self.send_json::<ItemFilterResponse>(request).await?;
+
+    Ok(response.map(|response| response.filter))
+  }
+}
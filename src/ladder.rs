@@ -0,0 +1,251 @@
+use chrono::{DateTime, Utc};
+use derive_builder::Builder;
+use futures::{stream, Stream, TryStreamExt};
+use serde::{Deserialize, Serialize};
+
+use crate::{ApiResponse, Error, League, PoEApi, Realm, Result, TokenProvider};
+
+pub const MAX_LADDER_LIMIT: u32 = 500;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LeagueLadder {
+  pub league: League,
+  pub ladder: Ladder,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Ladder {
+  pub total: u32,
+  pub cached_since: Option<DateTime<Utc>>,
+  pub entries: Vec<LadderEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LadderEntry {
+  pub rank: u32,
+  pub dead: Option<bool>,
+  pub retired: Option<bool>,
+  pub ineligible: Option<bool>,
+  pub public: Option<bool>,
+  pub character: LadderCharacter,
+  pub account: Option<Account>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LadderCharacter {
+  pub id: String,
+  pub name: String,
+  pub level: u32,
+  pub class: String,
+  pub time: Option<u32>,
+  pub score: Option<u32>,
+  pub progress: Option<serde_json::Value>,
+  pub experience: Option<u64>,
+  pub depth: Option<LadderDepth>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LadderDepth {
+  pub default: Option<u32>,
+  pub solo: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Account {
+  pub name: String,
+  pub realm: Option<String>,
+  pub guild: Option<AccountGuild>,
+  pub challenges: Option<AccountChallenges>,
+  pub twitch: Option<AccountTwitch>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountGuild {
+  pub id: u32,
+  pub name: String,
+  pub tag: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountChallenges {
+  pub set: Option<String>,
+  pub completed: u32,
+  pub max: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountTwitch {
+  pub name: String,
+  pub stream: Option<AccountTwitchStream>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountTwitchStream {
+  pub name: String,
+  pub image: String,
+  pub status: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LeagueEventLadder {
+  pub league: League,
+  pub ladder: EventLadder,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventLadder {
+  pub total: u32,
+  pub entries: Vec<EventLadderEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventLadderEntry {
+  pub rank: u32,
+  pub ineligible: Option<bool>,
+  pub time: Option<u32>,
+  pub private_league: PrivateLeague,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrivateLeague {
+  pub name: String,
+  pub url: String,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LadderSort {
+  Xp,
+  Depth,
+  DepthSolo,
+  Ancestor,
+  Time,
+  Score,
+  Class,
+}
+
+/// `sort` and `class` only apply to league ladders.
+#[derive(Debug, Clone, Default, Serialize, Builder)]
+#[builder(
+  pattern = "owned",
+  setter(into, strip_option),
+  default,
+  build_fn(error = "Error")
+)]
+pub struct LadderOptions {
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub realm: Option<Realm>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub sort: Option<LadderSort>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub class: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub limit: Option<u32>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub offset: Option<u32>,
+}
+
+impl PoEApi {
+  pub async fn get_league_ladder(
+    &self,
+    token: &(impl TokenProvider + ?Sized),
+    league: &str,
+    options: &LadderOptions,
+  ) -> Result<ApiResponse<LeagueLadder>> {
+    let options = LadderOptions {
+      realm: self.resolve_realm(options.realm),
+      ..options.clone()
+    };
+
+    let request = self
+      .get(&format!("/league/{league}/ladder"))?
+      .query(&options)
+      .bearer_auth(token.bearer_token(self).await?);
+
+    self.send_json(request).await
+  }
+
+  pub async fn get_event_ladder(
+    &self,
+    token: &(impl TokenProvider + ?Sized),
+    league: &str,
+    options: &LadderOptions,
+  ) -> Result<ApiResponse<LeagueEventLadder>> {
+    let options = LadderOptions {
+      realm: self.resolve_realm(options.realm),
+      ..options.clone()
+    };
+
+    let request = self
+      .get(&format!("/league/{league}/event-ladder"))?
+      .query(&options)
+      .bearer_auth(token.bearer_token(self).await?);
+
+    self.send_json(request).await
+  }
+
+  /// Walks every page of a league ladder starting at `options.offset`, `options.limit` entries
+  /// (500 by default) at a time.
+  pub fn league_ladder_stream<'a>(
+    &'a self,
+    token: &'a (impl TokenProvider + ?Sized),
+    league: &'a str,
+    options: LadderOptions,
+  ) -> impl Stream<Item = Result<LadderEntry>> + 'a {
+    paginate(options, move |options| async move {
+      let response = self.get_league_ladder(token, league, &options).await?;
+      let ladder = response.into_inner().ladder;
+
+      Ok((ladder.entries, ladder.total))
+    })
+  }
+
+  pub fn event_ladder_stream<'a>(
+    &'a self,
+    token: &'a (impl TokenProvider + ?Sized),
+    league: &'a str,
+    options: LadderOptions,
+  ) -> impl Stream<Item = Result<EventLadderEntry>> + 'a {
+    paginate(options, move |options| async move {
+      let response = self.get_event_ladder(token, league, &options).await?;
+      let ladder = response.into_inner().ladder;
+
+      Ok((ladder.entries, ladder.total))
+    })
+  }
+}
+
+fn paginate<T, F, Fut>(options: LadderOptions, fetch: F) -> impl Stream<Item = Result<T>>
+where
+  F: Fn(LadderOptions) -> Fut,
+  Fut: std::future::Future<Output = Result<(Vec<T>, u32)>>,
+{
+  let limit = options.limit.unwrap_or(MAX_LADDER_LIMIT);
+  let offset = options.offset.unwrap_or(0);
+  let pages = stream::try_unfold(Some(offset), move |offset| {
+    let options = LadderOptions {
+      limit: Some(limit),
+      offset,
+      ..options.clone()
+    };
+
+    let page = offset.map(|_| fetch(options));
+
+    async move {
+      let (Some(offset), Some(page)) = (offset, page) else {
+        return Ok::<_, Error>(None);
+      };
+
+      let (entries, total) = page.await?;
+      let next = offset + entries.len() as u32;
+      let next = (!entries.is_empty() && next < total).then_some(next);
+
+      Ok(Some((
+        stream::iter(entries.into_iter().map(Ok::<_, Error>)),
+        next,
+      )))
+    }
+  });
+
+  pages.try_flatten()
+}
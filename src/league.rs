@@ -0,0 +1,209 @@
+use chrono::{DateTime, Utc};
+use derive_builder::Builder;
+use serde::{Deserialize, Serialize};
+
+use crate::{ApiResponse, Error, PoEApi, Realm, Result, TokenProvider};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct League {
+  pub id: String,
+  pub realm: Option<String>,
+  pub name: Option<String>,
+  pub description: Option<String>,
+  pub category: Option<LeagueCategory>,
+  #[serde(default)]
+  pub rules: Vec<LeagueRule>,
+  pub register_at: Option<DateTime<Utc>>,
+  pub event: Option<bool>,
+  pub public: Option<bool>,
+  pub url: Option<String>,
+  pub start_at: Option<DateTime<Utc>>,
+  pub end_at: Option<DateTime<Utc>>,
+  pub timed_event: Option<bool>,
+  pub score_event: Option<bool>,
+  pub delve_event: Option<bool>,
+  pub ancestor_event: Option<bool>,
+  pub league_event: Option<bool>,
+}
+
+impl League {
+  /// Whether this is one of the currently running challenge leagues.
+  pub fn is_current(&self) -> bool {
+    self
+      .category
+      .as_ref()
+      .and_then(|category| category.current)
+      .unwrap_or(false)
+  }
+
+  pub fn is_event(&self) -> bool {
+    self.event.unwrap_or(false)
+  }
+
+  pub fn has_rule(&self, id: &str) -> bool {
+    self.rules.iter().any(|rule| rule.id == id)
+  }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LeagueCategory {
+  pub id: String,
+  pub current: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LeagueRule {
+  pub id: String,
+  pub name: String,
+  pub description: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LeagueAccount {
+  pub atlas_passives: Option<AtlasPassives>,
+  #[serde(default)]
+  pub atlas_passive_trees: Vec<AtlasPassiveTree>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AtlasPassives {
+  pub hashes: Vec<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AtlasPassiveTree {
+  pub name: String,
+  pub hashes: Vec<u32>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LeagueType {
+  Main,
+  Event,
+  Season,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Builder)]
+#[builder(
+  pattern = "owned",
+  setter(into, strip_option),
+  default,
+  build_fn(error = "Error")
+)]
+pub struct ListLeaguesOptions {
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub realm: Option<Realm>,
+  #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+  pub league_type: Option<LeagueType>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub season: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub limit: Option<u32>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub offset: Option<u32>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct LeaguesResponse {
+  leagues: Vec<League>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct LeagueResponse {
+  league: Option<League>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct LeagueAccountResponse {
+  league_account: LeagueAccount,
+}
+
+impl PoEApi {
+  pub async fn list_leagues(
+    &self,
+    token: &(impl TokenProvider + ?Sized),
+    options: &ListLeaguesOptions,
+  ) -> Result<ApiResponse<Vec<League>>> {
+    let options = ListLeaguesOptions {
+      realm: self.resolve_realm(options.realm),
+      ..options.clone()
+    };
+
+    let request = self
+      .get("/league")?
+      .query(&options)
+      .bearer_auth(token.bearer_token(self).await?);
+    let response = self.send_json::<LeaguesResponse>(request).await?;
+
+    Ok(response.map(|response| response.leagues))
+  }
+
+  pub async fn get_league(
+    &self,
+    token: &(impl TokenProvider + ?Sized),
+    league: &str,
+    realm: Option<Realm>,
+  ) -> Result<ApiResponse<Option<League>>> {
+    let request = self
+      .get(&format!("/league/{league}"))?
+      .query(&[("realm", self.resolve_realm(realm))])
+      .bearer_auth(token.bearer_token(self).await?);
+    let response = self.send_json::<LeagueResponse>(request).await?;
+
+    Ok(response.map(|response| response.league))
+  }
+
+  /// Finds the current challenge league, skipping event leagues and variants with extra rules
+  /// such as hardcore or solo self-found.
+  pub async fn get_current_league(
+    &self,
+    token: &(impl TokenProvider + ?Sized),
+    realm: Option<Realm>,
+  ) -> Result<ApiResponse<Option<League>>> {
+    let options = ListLeaguesOptions {
+      realm,
+      league_type: Some(LeagueType::Main),
+      ..Default::default()
+    };
+
+    let response = self.list_leagues(token, &options).await?;
+
+    Ok(response.map(|leagues| {
+      leagues
+        .into_iter()
+        .find(|league| league.is_current() && !league.is_event() && league.rules.is_empty())
+    }))
+  }
+
+  /// Lists the leagues the account can play in, including private leagues.
+  pub async fn get_account_leagues(
+    &self,
+    token: &(impl TokenProvider + ?Sized),
+    realm: Option<Realm>,
+  ) -> Result<ApiResponse<Vec<League>>> {
+    let realm = self.realm_segment(realm);
+    let request = self
+      .get(&format!("/account/leagues{realm}"))?
+      .bearer_auth(token.bearer_token(self).await?);
+    let response = self.send_json::<LeaguesResponse>(request).await?;
+
+    Ok(response.map(|response| response.leagues))
+  }
+
+  pub async fn get_league_account(
+    &self,
+    token: &(impl TokenProvider + ?Sized),
+    league: &str,
+    realm: Option<Realm>,
+  ) -> Result<ApiResponse<LeagueAccount>> {
+    let realm = self.realm_segment(realm);
+    let request = self
+      .get(&format!("/league-account{realm}/{league}"))?
+      .bearer_auth(token.bearer_token(self).await?);
+    let response = self.send_json::<LeagueAccountResponse>(request).await?;
+
+    Ok(response.map(|response| response.league_account))
+  }
+}
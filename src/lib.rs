@@ -1,23 +1,67 @@
-use std::net::{SocketAddr, ToSocketAddrs};
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::future::Future;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, ToSocketAddrs};
+use std::ops::{Deref, DerefMut};
 use std::str::FromStr;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use std::time::Duration;
 
-use derivative::Derivative;
 use derive_builder::Builder;
 use derive_more::From;
-use oauth2::basic::{BasicClient, BasicTokenResponse};
+use oauth2::basic::{
+  BasicClient, BasicErrorResponseType, BasicRequestTokenError, BasicTokenResponse,
+};
 use oauth2::{
-  AuthUrl, AuthorizationCode, ClientId, CsrfToken, PkceCodeChallenge, RedirectUrl, Scope, TokenUrl,
+  AuthType, AuthUrl, AuthorizationCode, ClientId, ClientSecret, CsrfToken, PkceCodeChallenge,
+  PkceCodeVerifier, RedirectUrl, RefreshToken, RequestTokenError, Scope, TokenUrl,
 };
 use reqwest::redirect::Policy;
-use reqwest::{Client, ClientBuilder, Method, RequestBuilder, Response, Url};
+use reqwest::{Client, ClientBuilder, Method, RequestBuilder, Response, StatusCode, Url};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::rate_limit::RateLimiter;
+
+pub use character::*;
+pub use currency_exchange::*;
+pub use item::*;
+pub use item_filter::*;
+pub use ladder::*;
+pub use league::*;
+pub use public_stash::*;
+pub use pvp::*;
+pub use rate_limit::{RateLimit, RateLimitRule, RateLimitState};
+pub use realm::*;
+pub use session::*;
+pub use stash::*;
+pub use token_store::*;
+
+mod character;
+mod currency_exchange;
+mod item;
+mod item_filter;
+mod ladder;
+mod league;
+mod public_stash;
+mod pvp;
+mod rate_limit;
+mod realm;
+mod session;
+mod stash;
+#[cfg(feature = "testing")]
+pub mod testing;
+mod token_store;
 
 pub const API_URL: &str = "https://api.pathofexile.com";
 pub const AUTH_URL: &str = "https://www.pathofexile.com/oauth/authorize";
 pub const TOKEN_URL: &str = "https://www.pathofexile.com/oauth/token";
+pub const REVOKE_URL: &str = "https://www.pathofexile.com/oauth/token/revoke";
+pub const DEFAULT_AUTHORIZATION_TIMEOUT: Duration = Duration::from_secs(300);
+pub const MAX_RATE_LIMIT_RETRIES: u32 = 2;
 pub const CLOSE_HTML: &str = r#"<!DOCTYPE html>
 <html lang="en">
 <head>
@@ -29,6 +73,9 @@ You can close this page.
 </body>
 </html>"#;
 
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+const MAX_REQUEST_HEAD: usize = 16 * 1024;
+
 pub type Result<T, E = Error> = std::result::Result<T, E>;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -48,32 +95,81 @@ pub enum Error {
   #[error(transparent)]
   UrlParseError(#[from] url::ParseError),
   #[error(transparent)]
+  SerdeJsonError(#[from] serde_json::Error),
+  #[error(transparent)]
   UninitializedFieldError(#[from] derive_builder::UninitializedFieldError),
   #[error("{error}: {error_description}")]
   PoEApiError {
     error: String,
     error_description: String,
   },
-  #[error("Failed to get authorization code")]
-  FailedToGetAuthorizationCode,
+  #[error(transparent)]
+  RequestTokenError(#[from] BasicRequestTokenError<oauth2::reqwest::Error<reqwest::Error>>),
+  #[error("Rate limited, retry after {retry_after:?}")]
+  RateLimited { retry_after: Duration },
+  #[error("A client secret is required for confidential client grants")]
+  MissingClientSecret,
+  #[error("A redirect url or address is required for the authorization code grant")]
+  MissingRedirect,
+  #[error("The redirect url {url} doesn't use port {port} the authorization server is bound to")]
+  RedirectPortMismatch { url: Url, port: u16 },
+  #[error("Timed out waiting for the authorization redirect")]
+  AuthorizationTimedOut,
+  #[error("Authorization was cancelled")]
+  AuthorizationCancelled,
+  #[error("Authorization failed: {error}")]
+  AuthorizationError {
+    error: String,
+    error_description: Option<String>,
+  },
+  #[error("The state of the authorization redirect does not match the request")]
+  AuthorizationStateMismatch,
+  #[error("The authorization redirect is missing the `{0}` parameter")]
+  MissingAuthorizationParameter(&'static str),
   #[error("{0}")]
   Custom(String),
   #[error(transparent)]
   BoxedError(#[from] Box<dyn std::error::Error + Send + Sync + 'static>),
 }
 
+impl Error {
+  /// Whether the authorization server rejected a code or refresh token as invalid, expired or
+  /// revoked.
+  pub fn is_invalid_grant(&self) -> bool {
+    matches!(
+      self,
+      Self::RequestTokenError(RequestTokenError::ServerResponse(response))
+        if *response.error() == BasicErrorResponseType::InvalidGrant
+    )
+  }
+}
+
 #[derive(Debug, Clone, Builder)]
 #[builder(pattern = "owned", setter(into))]
 pub struct PoEApiConfig {
   client_id: String,
+  #[builder(default, setter(strip_option))]
+  client_secret: Option<String>,
   version: String,
   contact_email: String,
-  #[builder(setter(custom))]
-  redirect_url: Url,
-  #[builder(setter(custom))]
+  #[builder(default, setter(strip_option))]
+  realm: Option<Realm>,
+  #[builder(setter(custom), default)]
+  redirect_url: Option<Url>,
+  #[builder(setter(custom), default)]
   redirect_addr: Vec<SocketAddr>,
   #[builder(default = "CLOSE_HTML.to_string()")]
   close_html: String,
+  #[builder(default = "DEFAULT_AUTHORIZATION_TIMEOUT")]
+  authorization_timeout: Duration,
+  #[builder(default = "API_URL.to_string()")]
+  api_url: String,
+  #[builder(default = "AUTH_URL.to_string()")]
+  auth_url: String,
+  #[builder(default = "TOKEN_URL.to_string()")]
+  token_url: String,
+  #[builder(default = "REVOKE_URL.to_string()")]
+  revoke_url: String,
 }
 
 impl PoEApiConfigBuilder {
@@ -82,7 +178,7 @@ impl PoEApiConfigBuilder {
     T: TryInto<Url>,
   {
     Ok(Self {
-      redirect_url: Some(value.try_into()?),
+      redirect_url: Some(Some(value.try_into()?)),
       ..self
     })
   }
@@ -98,28 +194,42 @@ impl PoEApiConfigBuilder {
   }
 }
 
-#[derive(Debug, Copy, Clone, From)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, From)]
 pub enum PoEApiScope {
   Account(PoEApiAccountScope),
+  Service(PoEApiServiceScope),
+}
+
+impl PoEApiScope {
+  pub const fn name(&'_ self) -> &'static str {
+    match self {
+      Self::Account(scope) => scope.name(),
+      Self::Service(scope) => scope.name(),
+    }
+  }
 }
 
 impl FromStr for PoEApiScope {
   type Err = Error;
 
   fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
-    PoEApiAccountScope::from_str(s).map(Into::<Self>::into)
+    match s.starts_with("service:") {
+      true => PoEApiServiceScope::from_str(s).map(Into::<Self>::into),
+      false => PoEApiAccountScope::from_str(s).map(Into::<Self>::into),
+    }
   }
 }
 
-impl ToString for PoEApiScope {
-  fn to_string(&self) -> String {
+impl Display for PoEApiScope {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
     match self {
-      PoEApiScope::Account(scope) => scope.to_string(),
+      PoEApiScope::Account(scope) => scope.fmt(f),
+      PoEApiScope::Service(scope) => scope.fmt(f),
     }
   }
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum PoEApiAccountScope {
   Profile,
   Leagues,
@@ -127,6 +237,7 @@ pub enum PoEApiAccountScope {
   Characters,
   LeagueAccounts,
   ItemFilter,
+  GuildStashes,
 }
 
 impl PoEApiAccountScope {
@@ -138,6 +249,7 @@ impl PoEApiAccountScope {
       Self::Characters => "account:characters",
       Self::LeagueAccounts => "account:league_accounts",
       Self::ItemFilter => "account:item_filter",
+      Self::GuildStashes => "account:guild:stashes",
     }
   }
 }
@@ -146,13 +258,14 @@ impl FromStr for PoEApiAccountScope {
   type Err = Error;
 
   fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
-    match s.trim_start_matches("account::") {
+    match s.trim_start_matches("account:") {
       "profile" => Ok(Self::Profile),
       "leagues" => Ok(Self::Leagues),
       "stashes" => Ok(Self::Stashes),
       "characters" => Ok(Self::Characters),
       "league_accounts" => Ok(Self::LeagueAccounts),
       "item_filter" => Ok(Self::ItemFilter),
+      "guild:stashes" => Ok(Self::GuildStashes),
       _ => Err(Error::Custom("Failed to parse account scope".into())),
     }
   }
@@ -171,17 +284,76 @@ impl Into<&str> for PoEApiAccountScope {
   }
 }
 
-impl ToString for PoEApiAccountScope {
-  fn to_string(&self) -> String {
-    self.as_ref().into()
+impl Display for PoEApiAccountScope {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    f.write_str(self.name())
+  }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum PoEApiServiceScope {
+  Leagues,
+  LeaguesLadder,
+  PvpMatches,
+  PvpMatchesLadder,
+  PublicStashes,
+  CurrencyExchange,
+}
+
+impl PoEApiServiceScope {
+  pub const fn name(&'_ self) -> &'static str {
+    match self {
+      Self::Leagues => "service:leagues",
+      Self::LeaguesLadder => "service:leagues:ladder",
+      Self::PvpMatches => "service:pvp_matches",
+      Self::PvpMatchesLadder => "service:pvp_matches:ladder",
+      Self::PublicStashes => "service:psapi",
+      Self::CurrencyExchange => "service:cxapi",
+    }
+  }
+}
+
+impl FromStr for PoEApiServiceScope {
+  type Err = Error;
+
+  fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+    match s.trim_start_matches("service:") {
+      "leagues" => Ok(Self::Leagues),
+      "leagues:ladder" => Ok(Self::LeaguesLadder),
+      "pvp_matches" => Ok(Self::PvpMatches),
+      "pvp_matches:ladder" => Ok(Self::PvpMatchesLadder),
+      "psapi" => Ok(Self::PublicStashes),
+      "cxapi" => Ok(Self::CurrencyExchange),
+      _ => Err(Error::Custom("Failed to parse service scope".into())),
+    }
+  }
+}
+
+impl AsRef<str> for PoEApiServiceScope {
+  fn as_ref(&self) -> &'static str {
+    self.name()
+  }
+}
+
+#[allow(clippy::from_over_into)]
+impl Into<&str> for PoEApiServiceScope {
+  fn into(self) -> &'static str {
+    self.name()
+  }
+}
+
+impl Display for PoEApiServiceScope {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    f.write_str(self.name())
   }
 }
 
 #[derive(Debug)]
 pub struct PoEApi {
   config: PoEApiConfig,
-  server: AuthorizationServer,
   client: Client,
+  rate_limiter: RateLimiter,
+  token_store: Option<Arc<dyn TokenStore>>,
 }
 
 impl PoEApi {
@@ -194,13 +366,11 @@ impl PoEApi {
       client_id,
       version,
       contact_email,
-      redirect_addr,
       ..
     } = &config;
 
     let user_agent = format!("OAuth {client_id}/{version} (contact: {contact_email})");
 
-    let server = AuthorizationServer::new(redirect_addr.as_slice())?;
     let client = builder
       .user_agent(user_agent)
       .redirect(Policy::none())
@@ -208,34 +378,239 @@ impl PoEApi {
 
     Ok(Self {
       config,
-      server,
       client,
+      rate_limiter: RateLimiter::default(),
+      token_store: None,
     })
   }
 
-  fn request(&self, method: Method, endpoint: &str) -> Result<RequestBuilder> {
-    let url = api_url(endpoint)?;
+  pub fn with_token_store(self, token_store: impl TokenStore + 'static) -> Self {
+    Self {
+      token_store: Some(Arc::new(token_store)),
+      ..self
+    }
+  }
+
+  pub fn token_store(&self) -> Option<&dyn TokenStore> {
+    self.token_store.as_deref()
+  }
+
+  pub(crate) async fn save_token(&self, account: &str, token: &SessionToken) -> Result<()> {
+    match &self.token_store {
+      Some(store) => store.save(&self.config.client_id, account, token).await,
+      None => Ok(()),
+    }
+  }
+
+  pub(crate) async fn delete_token(&self, account: &str) -> Result<()> {
+    match &self.token_store {
+      Some(store) => store.delete(&self.config.client_id, account).await,
+      None => Ok(()),
+    }
+  }
+
+  /// Resumes the stored session of `account` if it covers `scopes`, refreshing it if needed, and
+  /// only falls back to [`Self::get_token`] when there is none. New tokens are saved to the
+  /// token store, as are refreshed ones later on.
+  pub async fn login<S, F, T, R>(&self, account: &str, scopes: S, callback: F) -> Result<Session>
+  where
+    S::Item: Into<PoEApiScope>,
+    S: IntoIterator,
+    F: FnOnce(Url) -> R,
+    R: Into<Result<T, Error>>,
+  {
+    let scopes = scopes.into_iter().map(Into::into).collect::<Vec<_>>();
+
+    if let Some(store) = &self.token_store {
+      let stored = store.load(&self.config.client_id, account).await?;
+      let stored = stored.filter(|token| {
+        scopes
+          .iter()
+          .all(|scope| token.scopes.iter().any(|it| it == scope.name()))
+      });
+
+      if let Some(token) = stored {
+        let session = Session::with_account(token, account);
+        let token = session.token().await;
+
+        // Only a grant the server rejected needs a new login, anything else is worth reporting.
+        let usable = match (token.is_expired(), &token.refresh_token) {
+          (false, _) => true,
+          (true, None) => false,
+          (true, Some(_)) => match session.refresh(self).await {
+            Ok(()) => true,
+            Err(error) if error.is_invalid_grant() => false,
+            Err(error) => return Err(error),
+          },
+        };
+
+        if usable {
+          return Ok(session);
+        }
+      }
+    }
+
+    let names = scopes
+      .iter()
+      .map(|scope| scope.name().to_string())
+      .collect();
+    let mut token = SessionToken::from(self.get_token(scopes, callback).await?);
+
+    // The scope is optional in the token response when it is the one that was requested.
+    if token.scopes.is_empty() {
+      token.scopes = names;
+    }
+
+    self.save_token(account, &token).await?;
+
+    Ok(Session::with_account(token, account))
+  }
+
+  pub(crate) fn request(&self, method: Method, endpoint: &str) -> Result<RequestBuilder> {
+    let url = api_url(&self.config.api_url, endpoint)?;
 
     Ok(self.client.request(method, url))
   }
 
-  fn get(&self, endpoint: &str) -> Result<RequestBuilder> {
+  /// The realm of a request, falling back to the configured default.
+  pub(crate) fn resolve_realm(&self, realm: Option<Realm>) -> Option<Realm> {
+    realm.or(self.config.realm)
+  }
+
+  /// The path segment of endpoints taking the realm in their path, PC is the default and omitted.
+  pub(crate) fn realm_segment(&self, realm: Option<Realm>) -> String {
+    match self.resolve_realm(realm) {
+      None | Some(Realm::Pc) => String::new(),
+      Some(realm) => format!("/{realm}"),
+    }
+  }
+
+  pub(crate) fn get(&self, endpoint: &str) -> Result<RequestBuilder> {
     self.request(Method::GET, endpoint)
   }
 
-  pub async fn get_profile(&self, token: &str) -> Result<Profile> {
+  pub(crate) fn post(&self, endpoint: &str) -> Result<RequestBuilder> {
+    self.request(Method::POST, endpoint)
+  }
+
+  pub(crate) async fn send_json<T>(&self, request: RequestBuilder) -> Result<ApiResponse<T>>
+  where
+    T: DeserializeOwned,
+  {
+    let response = request.send_checked(&self.rate_limiter).await?;
+    let rate_limit = RateLimitState::from_headers(response.headers());
+    let data = response.json().await?;
+
+    Ok(ApiResponse { data, rate_limit })
+  }
+
+  /// The last known state of a rate limit policy, e.g. `character-request-limit`.
+  pub fn rate_limit_state(&self, policy: &str) -> Option<RateLimitState> {
+    self.rate_limiter.state(policy)
+  }
+
+  pub fn rate_limit_states(&self) -> Vec<RateLimitState> {
+    self.rate_limiter.states()
+  }
+
+  pub async fn get_profile(
+    &self,
+    token: &(impl TokenProvider + ?Sized),
+  ) -> Result<ApiResponse<Profile>> {
+    self
+      .send_json(
+        self
+          .get("/profile")?
+          .bearer_auth(token.bearer_token(self).await?),
+      )
+      .await
+  }
+
+  fn oauth_client(&self) -> Result<BasicClient> {
+    let client = BasicClient::new(
+      ClientId::new(self.config.client_id.to_string()),
+      self.config.client_secret.clone().map(ClientSecret::new),
+      AuthUrl::new(self.config.auth_url.clone())?,
+      Some(TokenUrl::new(self.config.token_url.clone())?),
+    )
+    .set_auth_type(AuthType::RequestBody);
+
+    Ok(client)
+  }
+
+  pub async fn refresh_token(&self, refresh_token: &str) -> Result<BasicTokenResponse> {
     self
-      .get("/profile")?
-      .bearer_auth(token)
-      .send_checked()
-      .await?
-      .json()
+      .oauth_client()?
+      .exchange_refresh_token(&RefreshToken::new(refresh_token.to_string()))
+      .request_async(oauth2::reqwest::async_http_client)
       .await
       .map_err(Into::into)
   }
 
-  pub fn close_authorization_server(&self) {
-    self.server.close_handle.store(true, Ordering::SeqCst)
+  /// Revokes an access or refresh token. Revoking a token that is already invalid succeeds.
+  pub async fn revoke_token(&self, token: &str) -> Result<()> {
+    let mut form = vec![
+      ("client_id", self.config.client_id.as_str()),
+      ("token", token),
+    ];
+
+    if let Some(client_secret) = &self.config.client_secret {
+      form.push(("client_secret", client_secret));
+    }
+
+    self
+      .client
+      .post(&self.config.revoke_url)
+      .form(&form)
+      .send_checked(&self.rate_limiter)
+      .await?;
+
+    Ok(())
+  }
+
+  /// Revokes both tokens of `session` and removes it from the token store. The stored token is
+  /// removed even if revoking fails, in which case the error is returned afterwards.
+  pub async fn logout(&self, session: &Session) -> Result<()> {
+    let token = session.token().await;
+
+    let mut revoked = Ok(());
+
+    if let Some(refresh_token) = &token.refresh_token {
+      revoked = self.revoke_token(refresh_token).await;
+    }
+
+    revoked = revoked.and(self.revoke_token(&token.access_token).await);
+
+    if let Some(account) = session.account() {
+      self.delete_token(account).await?;
+    }
+
+    revoked
+  }
+
+  /// Gets a token for the `service:*` scopes using the client credentials grant, which is only
+  /// available to confidential clients.
+  pub async fn get_service_token<S>(&self, scopes: S) -> Result<BasicTokenResponse>
+  where
+    S::Item: Into<PoEApiScope>,
+    S: IntoIterator,
+  {
+    if self.config.client_secret.is_none() {
+      return Err(Error::MissingClientSecret);
+    }
+
+    self
+      .oauth_client()?
+      .exchange_client_credentials()
+      .add_scopes(
+        scopes //
+          .into_iter()
+          .map(|s| s.into().to_string())
+          .map(Scope::new),
+      )
+      .request_async(oauth2::reqwest::async_http_client)
+      .await
+      .map_err(Into::into)
   }
 
   pub async fn get_token<S, F, T, R>(&self, scopes: S, callback: F) -> Result<BasicTokenResponse>
@@ -245,13 +620,85 @@ impl PoEApi {
     F: FnOnce(Url) -> R,
     R: Into<Result<T, Error>>,
   {
-    let client = BasicClient::new(
-      ClientId::new(self.config.client_id.to_string()),
-      None,
-      AuthUrl::new(AUTH_URL.into())?,
-      Some(TokenUrl::new(TOKEN_URL.to_string())?),
-    )
-    .set_redirect_uri(RedirectUrl::new(self.config.redirect_url.to_string())?);
+    self
+      .get_token_until(scopes, callback, std::future::pending())
+      .await
+  }
+
+  /// Like [`PoEApi::get_token`], but gives up with [`Error::AuthorizationCancelled`] once `cancel`
+  /// completes, e.g. when the user closes the login dialog. Only this call is affected.
+  pub async fn get_token_until<S, F, T, R>(
+    &self,
+    scopes: S,
+    callback: F,
+    cancel: impl Future<Output = ()>,
+  ) -> Result<BasicTokenResponse>
+  where
+    S::Item: Into<PoEApiScope>,
+    S: IntoIterator,
+    F: FnOnce(Url) -> R,
+    R: Into<Result<T, Error>>,
+  {
+    // The listener only lives for the duration of the flow, so the port is free otherwise.
+    let server = AuthorizationServer::bind(&self.config).await?;
+    let redirect_url = server.redirect_url(&self.config)?;
+    let authorization = self.authorization_request(redirect_url, scopes)?;
+
+    callback(authorization.auth_url.clone()).into()?;
+
+    let authorization_code = self
+      .wait_for_authorization(
+        server.get_authorization_code(&self.config, &authorization.csrf_token),
+        cancel,
+      )
+      .await?;
+
+    authorization.exchange(authorization_code).await
+  }
+
+  /// Like [`PoEApi::get_token`], for machines the browser can't redirect back to. Instead of
+  /// listening on `redirect_addr`, reads the redirected url from `input`, as pasted from the
+  /// address bar of the browser. A bare authorization code is accepted as well, but skips the
+  /// state check. Closing `input` cancels the flow.
+  pub async fn get_token_from_input<S, F, T, R, I>(
+    &self,
+    scopes: S,
+    callback: F,
+    input: I,
+  ) -> Result<BasicTokenResponse>
+  where
+    S::Item: Into<PoEApiScope>,
+    S: IntoIterator,
+    F: FnOnce(Url) -> R,
+    R: Into<Result<T, Error>>,
+    I: AsyncBufRead + Unpin,
+  {
+    let Some(redirect_url) = self.config.redirect_url.clone() else {
+      return Err(Error::MissingRedirect);
+    };
+
+    let authorization = self.authorization_request(redirect_url, scopes)?;
+
+    callback(authorization.auth_url.clone()).into()?;
+
+    let authorization_code = self
+      .wait_for_authorization(
+        read_authorization_code(input, &authorization.csrf_token),
+        std::future::pending(),
+      )
+      .await?;
+
+    authorization.exchange(authorization_code).await
+  }
+
+  fn authorization_request<S>(&self, redirect_url: Url, scopes: S) -> Result<AuthorizationRequest>
+  where
+    S::Item: Into<PoEApiScope>,
+    S: IntoIterator,
+  {
+    let client = self
+      .oauth_client()?
+      .set_redirect_uri(RedirectUrl::from_url(redirect_url));
 
     let (pkce_challenge, pkce_verifier) = PkceCodeChallenge::new_random_sha256();
     let (auth_url, csrf_token) = client
@@ -265,75 +712,283 @@ impl PoEApi {
       .set_pkce_challenge(pkce_challenge)
       .url();
 
-    callback(auth_url).into()?;
+    Ok(AuthorizationRequest {
+      client,
+      auth_url,
+      csrf_token,
+      pkce_verifier,
+    })
+  }
+
+  /// Waits for `authorization` until `authorization_timeout` passes or `cancel` completes.
+  async fn wait_for_authorization(
+    &self,
+    authorization: impl Future<Output = Result<String>>,
+    cancel: impl Future<Output = ()>,
+  ) -> Result<String> {
+    tokio::select! {
+      code = authorization => code,
+      _ = cancel => Err(Error::AuthorizationCancelled),
+      _ = tokio::time::sleep(self.config.authorization_timeout) => Err(Error::AuthorizationTimedOut),
+    }
+  }
+}
 
-    let authorization_code = self
-      .server
-      .get_authorization_code(&self.config, csrf_token)?;
+/// An authorization code grant in progress, shared by the loopback and the pasted flows.
+struct AuthorizationRequest {
+  client: BasicClient,
+  auth_url: Url,
+  csrf_token: CsrfToken,
+  pkce_verifier: PkceCodeVerifier,
+}
 
-    client
+impl AuthorizationRequest {
+  async fn exchange(self, authorization_code: String) -> Result<BasicTokenResponse> {
+    self
+      .client
       .exchange_code(AuthorizationCode::new(authorization_code))
-      .set_pkce_verifier(pkce_verifier)
+      .set_pkce_verifier(self.pkce_verifier)
       .request_async(oauth2::reqwest::async_http_client)
       .await
-      .map_err(|_| Error::FailedToGetAuthorizationCode)
+      .map_err(Into::into)
   }
 }
 
-#[derive(Derivative)]
-#[derivative(Debug)]
+#[derive(Debug)]
 pub(crate) struct AuthorizationServer {
-  #[derivative(Debug = "ignore")]
-  server: tiny_http::Server,
-  close_handle: AtomicBool,
+  listener: TcpListener,
 }
 
 impl AuthorizationServer {
-  pub fn new(addr: impl ToSocketAddrs) -> Result<Self> {
+  /// Binds `redirect_addr`, or the address of `redirect_url` when no address is set. Port `0`
+  /// picks a free port.
+  pub async fn bind(config: &PoEApiConfig) -> Result<Self> {
+    let addrs = match (&config.redirect_addr, &config.redirect_url) {
+      (addrs, _) if !addrs.is_empty() => addrs.clone(),
+      (_, Some(url)) => url.socket_addrs(|| None)?,
+      _ => return Err(Error::MissingRedirect),
+    };
+
     Ok(Self {
-      server: tiny_http::Server::http(addr)?,
-      close_handle: AtomicBool::new(false),
+      listener: TcpListener::bind(addrs.as_slice()).await?,
     })
   }
 
-  pub fn get_authorization_code(&self, config: &PoEApiConfig, state: CsrfToken) -> Result<String> {
-    while let Ok(request) = self.server.recv_timeout(Duration::from_millis(100)) {
-      let Some(request) = request else {
-        if self.close_handle.load(Ordering::SeqCst) {
-          return Err(Error::FailedToGetAuthorizationCode);
-        }
+  /// The redirect url with the port that was actually bound, derived from the local address if
+  /// none was configured. A port `0` in the url is replaced with the bound port. When the port
+  /// was picked by the OS but the url names a fixed one, the browser would be sent to a port
+  /// nobody listens on, so that is an error rather than silently rewritten.
+  pub fn redirect_url(&self, config: &PoEApiConfig) -> Result<Url> {
+    let addr = self.listener.local_addr()?;
+    let ephemeral = config.redirect_addr.iter().any(|addr| addr.port() == 0);
 
-        continue;
-      };
+    match &config.redirect_url {
+      Some(url) if url.port() == Some(0) => {
+        let mut url = url.clone();
 
-      let url = format!("{}{}", config.redirect_url, request.url());
-      let url = Url::parse(&url)?;
-      let query = url.query_pairs().collect::<Vec<_>>();
+        url
+          .set_port(Some(addr.port()))
+          .map_err(|_| Error::Custom(format!("Invalid redirect url {url}")))?;
 
-      let query_state = query
-        .iter()
-        .find(|(key, _)| key == "state")
-        .map(|(_, value)| value);
+        Ok(url)
+      }
+      Some(url) if ephemeral && url.port_or_known_default() != Some(addr.port()) => {
+        Err(Error::RedirectPortMismatch {
+          url: url.clone(),
+          port: addr.port(),
+        })
+      }
+      Some(url) => Ok(url.clone()),
+      None => {
+        let ip = match addr.ip() {
+          ip if ip.is_unspecified() && ip.is_ipv4() => Ipv4Addr::LOCALHOST.into(),
+          ip if ip.is_unspecified() => Ipv6Addr::LOCALHOST.into(),
+          ip => ip,
+        };
 
-      let query_code = query
-        .iter()
-        .find(|(key, _)| key == "code")
-        .map(|(_, value)| value);
+        Ok(Url::parse(&format!(
+          "http://{}",
+          SocketAddr::new(ip, addr.port())
+        ))?)
+      }
+    }
+  }
 
-      match (query_state, query_code) {
-        (Some(query_state), Some(query_code)) if state.secret() == query_state => {
-          request.respond(tiny_http::Response::from_data(config.close_html.as_bytes()))?;
+  /// Waits for the redirect carrying the authorization code, or the error the provider
+  /// redirected with. Only the provider knows the state, so a callback without the right one may
+  /// come from any local process or web page. It is answered with an error but doesn't end the
+  /// flow.
+  pub async fn get_authorization_code(
+    &self,
+    config: &PoEApiConfig,
+    state: &CsrfToken,
+  ) -> Result<String> {
+    loop {
+      let (mut stream, _) = self.listener.accept().await?;
 
-          return Ok(query_code.to_string());
-        }
-        _ => {
-          request
-            .respond(tiny_http::Response::from_string("Invalid Query").with_status_code(422))?;
+      // A browser may open connections it never sends anything on, don't let those block others.
+      let target =
+        match tokio::time::timeout(REQUEST_TIMEOUT, read_request_target(&mut stream)).await {
+          Ok(Ok(target)) => target,
+          _ => continue,
+        };
+
+      let url = Url::parse("http://localhost")?.join(&target)?;
+
+      // Anything else the browser asks for, like the favicon, is not the redirect.
+      let Some(result) = parse_authorization_redirect(&url, state) else {
+        let _ = respond(&mut stream, "404 Not Found", "text/plain", "Not Found").await;
+
+        continue;
+      };
+
+      let _ = match &result {
+        Ok(_) => respond(&mut stream, "200 OK", "text/html", &config.close_html).await,
+        Err(error) => {
+          respond(
+            &mut stream,
+            "400 Bad Request",
+            "text/plain",
+            &error.to_string(),
+          )
+          .await
         }
+      };
+
+      if matches!(result, Ok(_) | Err(Error::AuthorizationError { .. })) {
+        return result;
       }
     }
+  }
+}
+
+/// Extracts the authorization code from the query of a redirect, or the error the provider
+/// redirected with. Returns `None` if the url carries none of the redirect parameters.
+pub(crate) fn parse_authorization_redirect(url: &Url, state: &CsrfToken) -> Option<Result<String>> {
+  let query = url.query_pairs().collect::<HashMap<_, _>>();
 
-    Err(Error::FailedToGetAuthorizationCode)
+  if !["state", "code", "error"]
+    .iter()
+    .any(|key| query.contains_key(*key))
+  {
+    return None;
+  }
+
+  let result = match (query.get("state"), query.get("code"), query.get("error")) {
+    (None, _, _) => Err(Error::MissingAuthorizationParameter("state")),
+    (Some(query_state), _, _) if query_state != state.secret() => {
+      Err(Error::AuthorizationStateMismatch)
+    }
+    (_, _, Some(error)) => Err(Error::AuthorizationError {
+      error: error.to_string(),
+      error_description: query.get("error_description").map(|it| it.to_string()),
+    }),
+    (_, None, _) => Err(Error::MissingAuthorizationParameter("code")),
+    (_, Some(code), _) => Ok(code.to_string()),
+  };
+
+  Some(result)
+}
+
+/// Reads the first non-empty line of `input`, either the whole redirected url or just the code.
+async fn read_authorization_code(
+  mut input: impl AsyncBufRead + Unpin,
+  state: &CsrfToken,
+) -> Result<String> {
+  let mut line = String::new();
+
+  loop {
+    line.clear();
+
+    if input.read_line(&mut line).await? == 0 {
+      return Err(Error::AuthorizationCancelled);
+    }
+
+    let line = line.trim();
+
+    if line.is_empty() {
+      continue;
+    }
+
+    return match Url::parse(line) {
+      Ok(url) => parse_authorization_redirect(&url, state)
+        .unwrap_or(Err(Error::MissingAuthorizationParameter("code"))),
+      Err(_) => Ok(line.to_string()),
+    };
+  }
+}
+
+/// Reads the head of an HTTP request and returns its target, e.g. `/?code=...&state=...`.
+async fn read_request_target(stream: &mut TcpStream) -> Result<String> {
+  let mut head = Vec::new();
+  let mut buffer = [0; 1024];
+
+  while !head.windows(4).any(|window| window == b"\r\n\r\n") {
+    let read = stream.read(&mut buffer).await?;
+
+    if read == 0 || head.len() + read > MAX_REQUEST_HEAD {
+      return Err(Error::Custom("Invalid authorization request".into()));
+    }
+
+    head.extend_from_slice(&buffer[..read]);
+  }
+
+  String::from_utf8_lossy(&head)
+    .lines()
+    .next()
+    .and_then(|line| line.split_whitespace().nth(1))
+    .map(Into::into)
+    .ok_or_else(|| Error::Custom("Invalid authorization request".into()))
+}
+
+async fn respond(
+  stream: &mut TcpStream,
+  status: &str,
+  content_type: &str,
+  body: &str,
+) -> Result<()> {
+  let response = format!(
+    "HTTP/1.1 {status}\r\nContent-Type: {content_type}; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+    body.len()
+  );
+
+  stream.write_all(response.as_bytes()).await?;
+  stream.shutdown().await?;
+
+  Ok(())
+}
+
+/// A typed response along with the rate limit state reported alongside it.
+#[derive(Debug, Clone)]
+pub struct ApiResponse<T> {
+  pub data: T,
+  pub rate_limit: Option<RateLimitState>,
+}
+
+impl<T> ApiResponse<T> {
+  pub fn into_inner(self) -> T {
+    self.data
+  }
+
+  pub fn map<U>(self, f: impl FnOnce(T) -> U) -> ApiResponse<U> {
+    ApiResponse {
+      data: f(self.data),
+      rate_limit: self.rate_limit,
+    }
+  }
+}
+
+impl<T> Deref for ApiResponse<T> {
+  type Target = T;
+
+  fn deref(&self) -> &Self::Target {
+    &self.data
+  }
+}
+
+impl<T> DerefMut for ApiResponse<T> {
+  fn deref_mut(&mut self) -> &mut Self::Target {
+    &mut self.data
   }
 }
 
@@ -352,23 +1007,64 @@ pub struct ProfileGuildOrTwitch {
   name: String,
 }
 
-pub(crate) fn api_url(endpoint: &str) -> Result<Url> {
-  format!("{API_URL}{endpoint}").parse().map_err(Into::into)
+pub(crate) fn api_url(base: &str, endpoint: &str) -> Result<Url> {
+  let base = base.trim_end_matches('/');
+
+  format!("{base}{endpoint}").parse().map_err(Into::into)
 }
 
 #[async_trait::async_trait]
 pub(crate) trait RequestBuilderExt2 {
   type Error;
 
-  async fn send_checked(self) -> Result<Response, Self::Error>;
+  async fn send_checked(self, rate_limiter: &RateLimiter) -> Result<Response, Self::Error>;
 }
 
 #[async_trait::async_trait]
 impl RequestBuilderExt2 for RequestBuilder {
   type Error = Error;
 
-  async fn send_checked(self) -> Result<Response, Self::Error> {
-    let response = self.send().await?;
+  async fn send_checked(self, rate_limiter: &RateLimiter) -> Result<Response, Self::Error> {
+    let (client, request) = self.build_split();
+    let mut request = request?;
+    let route = rate_limit::route(request.method(), request.url());
+    let mut retries = 0;
+
+    let response = loop {
+      let delay = rate_limiter.acquire(&route);
+
+      if !delay.is_zero() {
+        tokio::time::sleep(delay).await;
+      }
+
+      let retry = request.try_clone();
+      let response = client.execute(request).await?;
+
+      rate_limiter.update(&route, response.headers());
+
+      if response.status() != StatusCode::TOO_MANY_REQUESTS {
+        break response;
+      }
+
+      let retry_after = rate_limit::retry_after(response.headers())
+        .unwrap_or_else(|| rate_limiter.restriction(&route));
+
+      let restricted = rate_limiter.restrict(&route, retry_after);
+
+      match retry {
+        Some(retry) if retries < MAX_RATE_LIMIT_RETRIES => {
+          // Without a known policy the limiter can't hold the retry back, so wait here instead.
+          if !restricted {
+            tokio::time::sleep(retry_after).await;
+          }
+
+          request = retry;
+          retries += 1;
+        }
+        _ => return Err(Error::RateLimited { retry_after }),
+      }
+    };
+
     let status = response.status();
 
     if status.is_client_error() || status.is_server_error() {
@@ -383,3 +1079,124 @@ impl RequestBuilderExt2 for RequestBuilder {
     Ok(response)
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn scopes_round_trip_through_their_names() {
+    let scopes: [PoEApiScope; 13] = [
+      PoEApiAccountScope::Profile.into(),
+      PoEApiAccountScope::Leagues.into(),
+      PoEApiAccountScope::Stashes.into(),
+      PoEApiAccountScope::Characters.into(),
+      PoEApiAccountScope::LeagueAccounts.into(),
+      PoEApiAccountScope::ItemFilter.into(),
+      PoEApiAccountScope::GuildStashes.into(),
+      PoEApiServiceScope::Leagues.into(),
+      PoEApiServiceScope::LeaguesLadder.into(),
+      PoEApiServiceScope::PvpMatches.into(),
+      PoEApiServiceScope::PvpMatchesLadder.into(),
+      PoEApiServiceScope::PublicStashes.into(),
+      PoEApiServiceScope::CurrencyExchange.into(),
+    ];
+
+    for scope in scopes {
+      assert_eq!(scope.to_string(), scope.name());
+      assert_eq!(PoEApiScope::from_str(scope.name()).unwrap(), scope);
+    }
+
+    assert_eq!(
+      PoEApiScope::from_str("account:guild:stashes").unwrap(),
+      PoEApiScope::Account(PoEApiAccountScope::GuildStashes)
+    );
+    assert!(PoEApiScope::from_str("account:unknown").is_err());
+    assert!(PoEApiScope::from_str("service:unknown").is_err());
+  }
+
+  fn redirect(query: &str) -> Option<Result<String>> {
+    let url = Url::parse(&format!("http://127.0.0.1:8088/{query}")).unwrap();
+
+    parse_authorization_redirect(&url, &CsrfToken::new("state".into()))
+  }
+
+  #[test]
+  fn parses_authorization_redirects() {
+    assert!(matches!(redirect("?code=abc&state=state"), Some(Ok(code)) if code == "abc"));
+    assert!(redirect("favicon.ico").is_none());
+    assert!(redirect("?unrelated=1").is_none());
+  }
+
+  #[test]
+  fn parses_authorization_error_redirects() {
+    assert!(matches!(
+      redirect("?error=access_denied&error_description=The+user+denied&state=state"),
+      Some(Err(Error::AuthorizationError { error, error_description }))
+        if error == "access_denied" && error_description.as_deref() == Some("The user denied")
+    ));
+    assert!(matches!(
+      redirect("?error=access_denied&state=state"),
+      Some(Err(Error::AuthorizationError {
+        error_description: None,
+        ..
+      }))
+    ));
+  }
+
+  #[test]
+  fn rejects_incomplete_or_forged_redirects() {
+    assert!(matches!(
+      redirect("?code=abc&state=other"),
+      Some(Err(Error::AuthorizationStateMismatch))
+    ));
+    assert!(matches!(
+      redirect("?error=access_denied&state=other"),
+      Some(Err(Error::AuthorizationStateMismatch))
+    ));
+    assert!(matches!(
+      redirect("?code=abc"),
+      Some(Err(Error::MissingAuthorizationParameter("state")))
+    ));
+    assert!(matches!(
+      redirect("?state=state"),
+      Some(Err(Error::MissingAuthorizationParameter("code")))
+    ));
+  }
+
+  async fn read(input: &str) -> Result<String> {
+    read_authorization_code(input.as_bytes(), &CsrfToken::new("state".into())).await
+  }
+
+  #[tokio::test]
+  async fn reads_pasted_redirect_urls() {
+    assert!(matches!(
+      read("http://localhost:8088/?code=abc&state=state\n").await,
+      Ok(code) if code == "abc"
+    ));
+    assert!(matches!(
+      read("http://localhost:8088/?code=abc&state=other\n").await,
+      Err(Error::AuthorizationStateMismatch)
+    ));
+    assert!(matches!(
+      read("http://localhost:8088/\n").await,
+      Err(Error::MissingAuthorizationParameter("code"))
+    ));
+  }
+
+  #[tokio::test]
+  async fn reads_pasted_codes() {
+    assert!(matches!(read("abc\n").await, Ok(code) if code == "abc"));
+    assert!(matches!(read("\n  \n\t abc \r\n").await, Ok(code) if code == "abc"));
+    assert!(matches!(read("abc").await, Ok(code) if code == "abc"));
+  }
+
+  #[tokio::test]
+  async fn treats_end_of_input_as_cancelled() {
+    assert!(matches!(read("").await, Err(Error::AuthorizationCancelled)));
+    assert!(matches!(
+      read("\n\n").await,
+      Err(Error::AuthorizationCancelled)
+    ));
+  }
+}
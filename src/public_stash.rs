@@ -0,0 +1,157 @@
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use futures::future::BoxFuture;
+use futures::{FutureExt, Stream};
+use serde::{Deserialize, Serialize};
+
+use crate::{ApiResponse, Item, PoEApi, Realm, Result, TokenProvider};
+
+/// How long [`PublicStashStream`] waits before polling again once it has caught up.
+pub const PUBLIC_STASH_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PublicStashes {
+  pub next_change_id: String,
+  pub stashes: Vec<PublicStashChange>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PublicStashChange {
+  pub id: String,
+  pub public: bool,
+  pub account_name: Option<String>,
+  pub stash: Option<String>,
+  pub last_character_name: Option<String>,
+  pub stash_type: String,
+  pub league: Option<String>,
+  #[serde(default)]
+  pub items: Vec<Item>,
+}
+
+impl PoEApi {
+  pub async fn get_public_stashes(
+    &self,
+    token: &(impl TokenProvider + ?Sized),
+    change_id: Option<&str>,
+    realm: Option<Realm>,
+  ) -> Result<ApiResponse<PublicStashes>> {
+    let realm = self.realm_segment(realm);
+    let request = self
+      .get(&format!("/public-stash-tabs{realm}"))?
+      .query(&[("id", change_id)])
+      .bearer_auth(token.bearer_token(self).await?);
+
+    self.send_json(request).await
+  }
+
+  /// Follows the public stash river from `change_id`, or from the beginning if it is `None`.
+  pub fn public_stash_stream<'a, T>(
+    &'a self,
+    token: &'a T,
+    change_id: Option<String>,
+    realm: Option<Realm>,
+  ) -> PublicStashStream<'a, T>
+  where
+    T: TokenProvider + ?Sized,
+  {
+    PublicStashStream {
+      api: self,
+      token,
+      next_change_id: change_id,
+      realm,
+      poll_interval: PUBLIC_STASH_POLL_INTERVAL,
+      delay: false,
+      pending: None,
+    }
+  }
+}
+
+/// Stream of non-empty [`PublicStashes`] batches that never ends on its own.
+///
+/// Errors are yielded without advancing, so polling again retries the same change id.
+pub struct PublicStashStream<'a, T: ?Sized> {
+  api: &'a PoEApi,
+  token: &'a T,
+  next_change_id: Option<String>,
+  realm: Option<Realm>,
+  poll_interval: Duration,
+  delay: bool,
+  pending: Option<BoxFuture<'a, Result<ApiResponse<PublicStashes>>>>,
+}
+
+impl<'a, T> PublicStashStream<'a, T>
+where
+  T: TokenProvider + ?Sized,
+{
+  pub fn with_poll_interval(self, poll_interval: Duration) -> Self {
+    Self {
+      poll_interval,
+      ..self
+    }
+  }
+
+  /// The change id the next batch will be requested with, persist it to resume later.
+  pub fn next_change_id(&self) -> Option<&str> {
+    self.next_change_id.as_deref()
+  }
+
+  fn fetch(&self) -> BoxFuture<'a, Result<ApiResponse<PublicStashes>>> {
+    let Self {
+      api, token, realm, ..
+    } = *self;
+    let change_id = self.next_change_id.clone();
+    let delay = self.delay.then_some(self.poll_interval);
+
+    async move {
+      if let Some(delay) = delay {
+        tokio::time::sleep(delay).await;
+      }
+
+      api
+        .get_public_stashes(token, change_id.as_deref(), realm)
+        .await
+    }
+    .boxed()
+  }
+}
+
+impl<'a, T> Stream for PublicStashStream<'a, T>
+where
+  T: TokenProvider + ?Sized,
+{
+  type Item = Result<ApiResponse<PublicStashes>>;
+
+  fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+    let this = self.get_mut();
+
+    loop {
+      let pending = match &mut this.pending {
+        Some(pending) => pending,
+        None => this.pending.insert(this.fetch()),
+      };
+
+      let result = futures::ready!(pending.as_mut().poll(cx));
+
+      this.pending = None;
+
+      match result {
+        Ok(response) => {
+          this.delay = response.stashes.is_empty();
+          this.next_change_id = Some(response.next_change_id.clone());
+
+          if !this.delay {
+            return Poll::Ready(Some(Ok(response)));
+          }
+        }
+        Err(error) => {
+          this.delay = true;
+
+          return Poll::Ready(Some(Err(error)));
+        }
+      }
+    }
+  }
+}
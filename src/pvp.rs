@@ -0,0 +1,183 @@
+use chrono::{DateTime, Utc};
+use derive_builder::Builder;
+use serde::{Deserialize, Serialize};
+
+use crate::{Account, ApiResponse, Error, PoEApi, Realm, Result, TokenProvider};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PvpMatch {
+  pub id: String,
+  pub realm: Option<String>,
+  pub start_at: Option<DateTime<Utc>>,
+  pub end_at: Option<DateTime<Utc>>,
+  pub url: Option<String>,
+  pub description: String,
+  pub glicko_ratings: bool,
+  pub pvp: bool,
+  pub style: PvpMatchStyle,
+  pub register_at: Option<DateTime<Utc>>,
+  pub complete: Option<bool>,
+  pub upcoming: Option<bool>,
+  pub in_progress: Option<bool>,
+  pub ascendancy: Option<bool>,
+  pub league: Option<String>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PvpMatchStyle {
+  Blitz,
+  Swiss,
+  Arena,
+  #[serde(other)]
+  Unknown,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PvpMatchType {
+  Upcoming,
+  Season,
+  League,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Builder)]
+#[builder(
+  pattern = "owned",
+  setter(into, strip_option),
+  default,
+  build_fn(error = "Error")
+)]
+pub struct ListPvpMatchesOptions {
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub realm: Option<Realm>,
+  #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+  pub match_type: Option<PvpMatchType>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub season: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub league: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Builder)]
+#[builder(
+  pattern = "owned",
+  setter(into, strip_option),
+  default,
+  build_fn(error = "Error")
+)]
+pub struct PvpLadderOptions {
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub realm: Option<Realm>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub limit: Option<u32>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub offset: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PvpMatchLadder {
+  #[serde(rename = "match")]
+  pub pvp_match: PvpMatch,
+  pub ladder: PvpLadder,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PvpLadder {
+  pub total: u32,
+  pub entries: Vec<PvpLadderTeamEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PvpLadderTeamEntry {
+  pub rank: u32,
+  pub rating: Option<u32>,
+  pub points: Option<u32>,
+  pub games_played: Option<u32>,
+  pub cumulative_opponent_points: Option<u32>,
+  pub last_game_time: Option<DateTime<Utc>>,
+  pub members: Vec<PvpLadderTeamMember>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PvpLadderTeamMember {
+  pub account: Account,
+  pub character: PvpCharacter,
+  pub public: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PvpCharacter {
+  pub id: String,
+  pub name: String,
+  pub level: u32,
+  pub class: String,
+  pub league: Option<String>,
+  pub score: Option<u32>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct PvpMatchesResponse {
+  matches: Vec<PvpMatch>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct PvpMatchResponse {
+  #[serde(rename = "match")]
+  pvp_match: Option<PvpMatch>,
+}
+
+impl PoEApi {
+  pub async fn list_pvp_matches(
+    &self,
+    token: &(impl TokenProvider + ?Sized),
+    options: &ListPvpMatchesOptions,
+  ) -> Result<ApiResponse<Vec<PvpMatch>>> {
+    let options = ListPvpMatchesOptions {
+      realm: self.resolve_realm(options.realm),
+      ..options.clone()
+    };
+
+    let request = self
+      .get("/pvp-match")?
+      .query(&options)
+      .bearer_auth(token.bearer_token(self).await?);
+    let response = self.send_json::<PvpMatchesResponse>(request).await?;
+
+    Ok(response.map(|response| response.matches))
+  }
+
+  pub async fn get_pvp_match(
+    &self,
+    token: &(impl TokenProvider + ?Sized),
+    id: &str,
+    realm: Option<Realm>,
+  ) -> Result<ApiResponse<Option<PvpMatch>>> {
+    let request = self
+      .get(&format!("/pvp-match/{id}"))?
+      .query(&[("realm", self.resolve_realm(realm))])
+      .bearer_auth(token.bearer_token(self).await?);
+    let response = self.send_json::<PvpMatchResponse>(request).await?;
+
+    Ok(response.map(|response| response.pvp_match))
+  }
+
+  pub async fn get_pvp_match_ladder(
+    &self,
+    token: &(impl TokenProvider + ?Sized),
+    id: &str,
+    options: &PvpLadderOptions,
+  ) -> Result<ApiResponse<PvpMatchLadder>> {
+    let options = PvpLadderOptions {
+      realm: self.resolve_realm(options.realm),
+      ..options.clone()
+    };
+
+    let request = self
+      .get(&format!("/pvp-match/{id}/ladder"))?
+      .query(&options)
+      .bearer_auth(token.bearer_token(self).await?);
+
+    self.send_json(request).await
+  }
+}
@@ -0,0 +1,514 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::{Method, Url};
+
+pub const RATE_LIMIT_POLICY_HEADER: &str = "X-Rate-Limit-Policy";
+pub const RATE_LIMIT_RULES_HEADER: &str = "X-Rate-Limit-Rules";
+
+const DEFAULT_RESTRICTION: Duration = Duration::from_secs(60);
+
+/// Snapshot of a rate limit policy as reported by the `X-Rate-Limit-*` headers of a response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RateLimitState {
+  pub policy: String,
+  pub rules: Vec<RateLimitRule>,
+}
+
+impl RateLimitState {
+  pub fn from_headers(headers: &HeaderMap) -> Option<Self> {
+    let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+
+    let policy = header(RATE_LIMIT_POLICY_HEADER)?.trim().to_string();
+    let rules = header(RATE_LIMIT_RULES_HEADER)?
+      .split(',')
+      .map(str::trim)
+      .filter(|rule| !rule.is_empty())
+      .filter_map(|rule| {
+        let limits = header(&format!("X-Rate-Limit-{rule}"))?;
+        let state = header(&format!("X-Rate-Limit-{rule}-State")).unwrap_or_default();
+
+        Some(RateLimitRule {
+          name: rule.to_string(),
+          limits: parse_limits(limits, state),
+        })
+      })
+      .collect();
+
+    Some(Self { policy, rules })
+  }
+
+  pub fn limits(&self) -> impl Iterator<Item = &RateLimit> {
+    self.rules.iter().flat_map(|rule| &rule.limits)
+  }
+
+  /// Requests left in the tightest window of the policy.
+  pub fn remaining(&self) -> u32 {
+    self
+      .limits()
+      .map(RateLimit::remaining)
+      .min()
+      .unwrap_or(u32::MAX)
+  }
+
+  pub fn is_restricted(&self) -> bool {
+    self.limits().any(RateLimit::is_restricted)
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RateLimitRule {
+  pub name: String,
+  pub limits: Vec<RateLimit>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct RateLimit {
+  pub max_hits: u32,
+  pub period: Duration,
+  pub restriction: Duration,
+  pub hits: u32,
+  pub restricted: Duration,
+}
+
+impl RateLimit {
+  pub fn remaining(&self) -> u32 {
+    self.max_hits.saturating_sub(self.hits)
+  }
+
+  pub fn is_restricted(&self) -> bool {
+    !self.restricted.is_zero()
+  }
+}
+
+#[derive(Debug)]
+struct Policy {
+  state: RateLimitState,
+  updated_at: Instant,
+  restricted_until: Option<Instant>,
+  sent: VecDeque<Instant>,
+}
+
+impl Policy {
+  fn new(state: RateLimitState, now: Instant) -> Self {
+    Self {
+      state,
+      updated_at: now,
+      restricted_until: None,
+      sent: VecDeque::new(),
+    }
+  }
+
+  fn longest_period(&self) -> Duration {
+    self
+      .state
+      .limits()
+      .map(|limit| limit.period)
+      .max()
+      .unwrap_or_default()
+  }
+
+  /// Earliest instant at which one more request fits inside every window of this policy.
+  fn next_slot(&self, now: Instant) -> Instant {
+    let mut slot = now.max(self.restricted_until.unwrap_or(now));
+
+    for limit in self.state.limits() {
+      if !limit.restricted.is_zero() {
+        slot = slot.max(self.updated_at + limit.restricted);
+      }
+
+      // The server state already covers everything sent before it was reported, so only
+      // requests sent afterwards are counted on top of it. The reported hits are assumed to
+      // expire all at once at the end of their window, which is the pessimistic case.
+      let mut expiries = self
+        .sent
+        .iter()
+        .filter(|sent| **sent > self.updated_at)
+        .map(|sent| (*sent + limit.period, 1))
+        .collect::<Vec<_>>();
+
+      expiries.push((self.updated_at + limit.period, limit.hits));
+      expiries.retain(|(expiry, _)| *expiry > slot);
+      expiries.sort_by_key(|(expiry, _)| *expiry);
+
+      let hits = expiries.iter().map(|(_, hits)| hits).sum::<u32>();
+      let mut excess = (hits + 1).saturating_sub(limit.max_hits);
+
+      for (expiry, hits) in expiries {
+        if excess == 0 {
+          break;
+        }
+
+        slot = slot.max(expiry);
+        excess = excess.saturating_sub(hits);
+      }
+    }
+
+    slot
+  }
+}
+
+#[derive(Debug, Default)]
+struct RateLimiterState {
+  policies: HashMap<String, Policy>,
+  /// Every policy seen on a route. A route can cover endpoints with different policies, e.g.
+  /// `GET /league` covers both the league list and the ladders.
+  routes: HashMap<String, Vec<String>>,
+}
+
+impl RateLimiterState {
+  fn policies_mut<'a>(&'a mut self, route: &str) -> impl Iterator<Item = &'a mut Policy> {
+    let Self { policies, routes } = self;
+    let names = routes.get(route).map(Vec::as_slice).unwrap_or_default();
+
+    policies
+      .iter_mut()
+      .filter(move |(name, _)| names.contains(name))
+      .map(|(_, policy)| policy)
+  }
+}
+
+/// Tracks the rate limit policies reported by the API and delays requests that would break them.
+#[derive(Debug, Default)]
+pub(crate) struct RateLimiter {
+  state: Mutex<RateLimiterState>,
+}
+
+impl RateLimiter {
+  /// Reserves a slot for a request to `route` and returns how long to wait before sending it.
+  /// When several policies were seen on `route`, the request waits for the strictest of them.
+  pub fn acquire(&self, route: &str) -> Duration {
+    let now = Instant::now();
+    let mut state = self.state.lock().unwrap();
+
+    let Some(slot) = state
+      .policies_mut(route)
+      .map(|policy| policy.next_slot(now))
+      .max()
+    else {
+      return Duration::ZERO;
+    };
+
+    for policy in state.policies_mut(route) {
+      let longest_period = policy.longest_period();
+
+      policy.sent.retain(|sent| *sent + longest_period > now);
+      policy.sent.push_back(slot);
+    }
+
+    slot - now
+  }
+
+  /// Updates the policy state from the headers of a response to `route`.
+  pub fn update(&self, route: &str, headers: &HeaderMap) {
+    let Some(rate_limit) = RateLimitState::from_headers(headers) else {
+      return;
+    };
+
+    let now = Instant::now();
+    let mut state = self.state.lock().unwrap();
+    let names = state.routes.entry(route.to_string()).or_default();
+
+    if !names.contains(&rate_limit.policy) {
+      names.push(rate_limit.policy.clone());
+    }
+
+    match state.policies.get_mut(&rate_limit.policy) {
+      Some(policy) => {
+        policy.state = rate_limit;
+        policy.updated_at = now;
+      }
+      None => {
+        let name = rate_limit.policy.clone();
+
+        state.policies.insert(name, Policy::new(rate_limit, now));
+      }
+    }
+  }
+
+  pub fn state(&self, policy: &str) -> Option<RateLimitState> {
+    let state = self.state.lock().unwrap();
+
+    state
+      .policies
+      .get(policy)
+      .map(|policy| policy.state.clone())
+  }
+
+  pub fn states(&self) -> Vec<RateLimitState> {
+    let state = self.state.lock().unwrap();
+
+    state
+      .policies
+      .values()
+      .map(|policy| policy.state.clone())
+      .collect()
+  }
+
+  /// The longest penalty of the policies of `route`, used when a 429 comes without
+  /// `Retry-After`.
+  pub fn restriction(&self, route: &str) -> Duration {
+    let mut state = self.state.lock().unwrap();
+
+    state
+      .policies_mut(route)
+      .flat_map(|policy| policy.state.limits())
+      .map(|limit| limit.restriction)
+      .max()
+      .unwrap_or(DEFAULT_RESTRICTION)
+  }
+
+  /// Blocks every request to the policies of `route` until `duration` has passed. Returns
+  /// `false` if no policy of `route` is known yet, so nothing could be blocked.
+  pub fn restrict(&self, route: &str, duration: Duration) -> bool {
+    let until = Instant::now() + duration;
+    let mut state = self.state.lock().unwrap();
+    let mut restricted = false;
+
+    for policy in state.policies_mut(route) {
+      policy.restricted_until = Some(policy.restricted_until.map_or(until, |it| it.max(until)));
+      restricted = true;
+    }
+
+    restricted
+  }
+}
+
+/// Requests are grouped by method and the first path segment, e.g. `GET /character`, which is
+/// how the API mostly assigns its policies.
+pub(crate) fn route(method: &Method, url: &Url) -> String {
+  let segment = url
+    .path_segments()
+    .and_then(|mut segments| segments.next())
+    .unwrap_or_default();
+
+  format!("{method} /{segment}")
+}
+
+pub(crate) fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+  headers
+    .get(RETRY_AFTER)?
+    .to_str()
+    .ok()?
+    .trim()
+    .parse()
+    .ok()
+    .map(Duration::from_secs)
+}
+
+/// Both headers are comma separated lists of `a:b:c` triples, `hits:period:restriction` for the
+/// limits and `hits:period:restricted` for the state, all periods in seconds.
+fn parse_limits(limits: &str, state: &str) -> Vec<RateLimit> {
+  fn triples(value: &str) -> impl Iterator<Item = (u32, u64, u64)> + '_ {
+    value.split(',').filter_map(|triple| {
+      let mut parts = triple.trim().splitn(3, ':');
+
+      Some((
+        parts.next()?.parse().ok()?,
+        parts.next()?.parse().ok()?,
+        parts.next()?.parse().ok()?,
+      ))
+    })
+  }
+
+  let state = triples(state).collect::<Vec<_>>();
+
+  triples(limits)
+    .map(|(max_hits, period, restriction)| {
+      let (hits, restricted) = state
+        .iter()
+        .find(|(_, state_period, _)| *state_period == period)
+        .map(|(hits, _, restricted)| (*hits, *restricted))
+        .unwrap_or_default();
+
+      RateLimit {
+        max_hits,
+        period: Duration::from_secs(period),
+        restriction: Duration::from_secs(restriction),
+        hits,
+        restricted: Duration::from_secs(restricted),
+      }
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use reqwest::header::HeaderValue;
+
+  use super::*;
+
+  fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+    pairs
+      .iter()
+      .map(|(name, value)| (*name, HeaderValue::from_str(value).unwrap()))
+      .fold(HeaderMap::new(), |mut headers, (name, value)| {
+        headers.insert(name, value);
+        headers
+      })
+  }
+
+  fn limit(max_hits: u32, period: u64, hits: u32, restricted: u64) -> RateLimit {
+    RateLimit {
+      max_hits,
+      period: Duration::from_secs(period),
+      restriction: Duration::from_secs(60),
+      hits,
+      restricted: Duration::from_secs(restricted),
+    }
+  }
+
+  fn policy(limits: Vec<RateLimit>, now: Instant) -> Policy {
+    let state = RateLimitState {
+      policy: "test".to_string(),
+      rules: vec![RateLimitRule {
+        name: "Ip".to_string(),
+        limits,
+      }],
+    };
+
+    Policy::new(state, now)
+  }
+
+  #[test]
+  fn parses_multiple_rules_with_their_state() {
+    let headers = headers(&[
+      ("X-Rate-Limit-Policy", "character-request-limit"),
+      ("X-Rate-Limit-Rules", "Ip, Account"),
+      ("X-Rate-Limit-Ip", "10:60:120,30:300:600"),
+      ("X-Rate-Limit-Ip-State", "1:60:0,30:300:42"),
+      ("X-Rate-Limit-Account", "3:5:60"),
+    ]);
+
+    let state = RateLimitState::from_headers(&headers).unwrap();
+
+    assert_eq!(state.policy, "character-request-limit");
+    assert_eq!(state.rules.len(), 2);
+    assert_eq!(state.rules[0].name, "Ip");
+    assert_eq!(
+      state.rules[0].limits,
+      [
+        RateLimit {
+          max_hits: 10,
+          period: Duration::from_secs(60),
+          restriction: Duration::from_secs(120),
+          hits: 1,
+          restricted: Duration::ZERO,
+        },
+        RateLimit {
+          max_hits: 30,
+          period: Duration::from_secs(300),
+          restriction: Duration::from_secs(600),
+          hits: 30,
+          restricted: Duration::from_secs(42),
+        },
+      ]
+    );
+    // Without a state header nothing is assumed to be used up.
+    assert_eq!(state.rules[1].name, "Account");
+    assert_eq!(state.rules[1].limits[0].hits, 0);
+
+    assert_eq!(state.remaining(), 0);
+    assert!(state.is_restricted());
+  }
+
+  #[test]
+  fn ignores_responses_without_a_policy() {
+    assert!(RateLimitState::from_headers(&HeaderMap::new()).is_none());
+  }
+
+  #[test]
+  fn next_slot_waits_until_the_window_frees_up() {
+    let now = Instant::now();
+
+    assert_eq!(policy(vec![limit(2, 10, 1, 0)], now).next_slot(now), now);
+    assert_eq!(
+      policy(vec![limit(2, 10, 2, 0)], now).next_slot(now),
+      now + Duration::from_secs(10)
+    );
+  }
+
+  #[test]
+  fn next_slot_counts_requests_sent_after_the_last_update() {
+    let now = Instant::now();
+    let mut policy = policy(vec![limit(2, 10, 0, 0)], now);
+
+    policy.sent.push_back(now + Duration::from_secs(1));
+    policy.sent.push_back(now + Duration::from_secs(2));
+
+    assert_eq!(policy.next_slot(now), now + Duration::from_secs(11));
+  }
+
+  #[test]
+  fn restricted_state_blocks_until_the_restriction_ends() {
+    let now = Instant::now();
+    let policy = policy(vec![limit(10, 10, 0, 30)], now);
+
+    assert_eq!(policy.next_slot(now), now + Duration::from_secs(30));
+    assert_eq!(
+      policy.next_slot(now + Duration::from_secs(31)),
+      now + Duration::from_secs(31)
+    );
+  }
+
+  fn policy_headers(policy: &str, limit: &str, state: &str) -> HeaderMap {
+    headers(&[
+      ("X-Rate-Limit-Policy", policy),
+      ("X-Rate-Limit-Rules", "Ip"),
+      ("X-Rate-Limit-Ip", limit),
+      ("X-Rate-Limit-Ip-State", state),
+    ])
+  }
+
+  #[test]
+  fn waits_for_the_strictest_policy_of_a_route() {
+    let limiter = RateLimiter::default();
+
+    // The ladder and the league list share a route but not a policy.
+    limiter.update(
+      "GET /league",
+      &policy_headers("ladder-request-limit", "1:10:60", "1:10:0"),
+    );
+    limiter.update(
+      "GET /league",
+      &policy_headers("league-request-limit", "30:10:60", "1:10:0"),
+    );
+
+    assert!(limiter.acquire("GET /league") > Duration::from_secs(9));
+    assert_eq!(limiter.acquire("GET /profile"), Duration::ZERO);
+  }
+
+  #[test]
+  fn restricts_only_known_routes() {
+    let limiter = RateLimiter::default();
+
+    assert!(!limiter.restrict("GET /profile", Duration::from_secs(5)));
+
+    limiter.update(
+      "GET /profile",
+      &policy_headers("profile-request-limit", "30:10:60", "1:10:0"),
+    );
+
+    assert!(limiter.restrict("GET /profile", Duration::from_secs(5)));
+    assert!(limiter.acquire("GET /profile") > Duration::from_secs(4));
+  }
+
+  #[test]
+  fn reads_retry_after() {
+    assert_eq!(
+      retry_after(&headers(&[("Retry-After", " 5 ")])),
+      Some(Duration::from_secs(5))
+    );
+    assert_eq!(retry_after(&headers(&[("Retry-After", "soon")])), None);
+    assert_eq!(retry_after(&HeaderMap::new()), None);
+  }
+
+  #[test]
+  fn routes_by_method_and_first_segment() {
+    let url = Url::parse("https://api.pathofexile.com/character/pc/Name?realm=pc").unwrap();
+
+    assert_eq!(route(&Method::GET, &url), "GET /character");
+  }
+}
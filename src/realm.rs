@@ -0,0 +1,53 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::Error;
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Realm {
+  #[default]
+  Pc,
+  Xbox,
+  Sony,
+  Poe2,
+}
+
+impl Realm {
+  pub const fn name(&'_ self) -> &'static str {
+    match self {
+      Self::Pc => "pc",
+      Self::Xbox => "xbox",
+      Self::Sony => "sony",
+      Self::Poe2 => "poe2",
+    }
+  }
+}
+
+impl FromStr for Realm {
+  type Err = Error;
+
+  fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+    match s {
+      "pc" => Ok(Self::Pc),
+      "xbox" => Ok(Self::Xbox),
+      "sony" => Ok(Self::Sony),
+      "poe2" => Ok(Self::Poe2),
+      _ => Err(Error::Custom("Failed to parse realm".into())),
+    }
+  }
+}
+
+impl AsRef<str> for Realm {
+  fn as_ref(&self) -> &'static str {
+    self.name()
+  }
+}
+
+impl Display for Realm {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    f.write_str(self.name())
+  }
+}
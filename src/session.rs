@@ -0,0 +1,171 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use oauth2::basic::BasicTokenResponse;
+use oauth2::TokenResponse;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::{PoEApi, Result};
+
+/// How long before expiry a [`Session`] refreshes its access token.
+pub const REFRESH_MARGIN: Duration = Duration::from_secs(60);
+
+/// Anything that can produce an access token for an authenticated request.
+#[async_trait::async_trait]
+pub trait TokenProvider: Send + Sync {
+  async fn bearer_token(&self, api: &PoEApi) -> Result<String>;
+}
+
+#[async_trait::async_trait]
+impl TokenProvider for str {
+  async fn bearer_token(&self, _api: &PoEApi) -> Result<String> {
+    Ok(self.to_string())
+  }
+}
+
+#[async_trait::async_trait]
+impl TokenProvider for String {
+  async fn bearer_token(&self, _api: &PoEApi) -> Result<String> {
+    Ok(self.clone())
+  }
+}
+
+#[async_trait::async_trait]
+impl TokenProvider for BasicTokenResponse {
+  async fn bearer_token(&self, _api: &PoEApi) -> Result<String> {
+    Ok(self.access_token().secret().clone())
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionToken {
+  pub access_token: String,
+  pub refresh_token: Option<String>,
+  pub expires_at: Option<DateTime<Utc>>,
+  #[serde(default)]
+  pub scopes: Vec<String>,
+}
+
+impl SessionToken {
+  pub fn is_expired(&self) -> bool {
+    self.expires_at.is_some_and(|expires_at| {
+      (expires_at - Utc::now())
+        .to_std()
+        .map_or(true, |left| left <= REFRESH_MARGIN)
+    })
+  }
+
+  /// Applies a token response, keeping the previous refresh token and scopes if the response
+  /// doesn't include new ones.
+  fn update(&mut self, response: BasicTokenResponse) {
+    let token = Self::from(response);
+
+    self.access_token = token.access_token;
+    self.expires_at = token.expires_at;
+    self.refresh_token = token.refresh_token.or(self.refresh_token.take());
+
+    if !token.scopes.is_empty() {
+      self.scopes = token.scopes;
+    }
+  }
+}
+
+impl From<BasicTokenResponse> for SessionToken {
+  fn from(response: BasicTokenResponse) -> Self {
+    Self {
+      access_token: response.access_token().secret().clone(),
+      refresh_token: response.refresh_token().map(|token| token.secret().clone()),
+      expires_at: response
+        .expires_in()
+        .and_then(|expires_in| chrono::Duration::from_std(expires_in).ok())
+        .map(|expires_in| Utc::now() + expires_in),
+      scopes: response
+        .scopes()
+        .into_iter()
+        .flatten()
+        .map(|scope| scope.to_string())
+        .collect(),
+    }
+  }
+}
+
+/// Holds the tokens of a logged in user and refreshes the access token when it is about to expire.
+#[derive(Debug)]
+pub struct Session {
+  token: Mutex<SessionToken>,
+  account: Option<String>,
+}
+
+impl Session {
+  pub fn new(token: SessionToken) -> Self {
+    Self {
+      token: Mutex::new(token),
+      account: None,
+    }
+  }
+
+  /// A session whose refreshed tokens are saved to the token store of the [`PoEApi`] under
+  /// `account`.
+  pub fn with_account(token: SessionToken, account: impl Into<String>) -> Self {
+    Self {
+      token: Mutex::new(token),
+      account: Some(account.into()),
+    }
+  }
+
+  pub fn account(&self) -> Option<&str> {
+    self.account.as_deref()
+  }
+
+  /// A copy of the current tokens, e.g. to persist them.
+  pub async fn token(&self) -> SessionToken {
+    self.token.lock().await.clone()
+  }
+
+  pub async fn refresh(&self, api: &PoEApi) -> Result<()> {
+    let mut token = self.token.lock().await;
+
+    self.refresh_locked(&mut token, api).await
+  }
+
+  async fn refresh_locked(&self, token: &mut SessionToken, api: &PoEApi) -> Result<()> {
+    let Some(refresh_token) = &token.refresh_token else {
+      return Err(crate::Error::Custom("Session has no refresh token".into()));
+    };
+
+    let response = api.refresh_token(refresh_token).await?;
+
+    token.update(response);
+
+    match &self.account {
+      Some(account) => api.save_token(account, token).await,
+      None => Ok(()),
+    }
+  }
+}
+
+impl From<SessionToken> for Session {
+  fn from(token: SessionToken) -> Self {
+    Self::new(token)
+  }
+}
+
+impl From<BasicTokenResponse> for Session {
+  fn from(response: BasicTokenResponse) -> Self {
+    Self::new(response.into())
+  }
+}
+
+#[async_trait::async_trait]
+impl TokenProvider for Session {
+  async fn bearer_token(&self, api: &PoEApi) -> Result<String> {
+    let mut token = self.token.lock().await;
+
+    if token.is_expired() && token.refresh_token.is_some() {
+      self.refresh_locked(&mut token, api).await?;
+    }
+
+    Ok(token.access_token.clone())
+  }
+}
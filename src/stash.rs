@@ -0,0 +1,128 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{ApiResponse, Item, PoEApi, Realm, Result, TokenProvider};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StashTab {
+  pub id: String,
+  pub parent: Option<String>,
+  pub folder: Option<String>,
+  pub name: String,
+  #[serde(rename = "type")]
+  pub stash_type: String,
+  pub index: Option<u32>,
+  pub metadata: StashTabMetadata,
+  pub children: Option<Vec<StashTab>>,
+  pub items: Option<Vec<Item>>,
+}
+
+impl StashTab {
+  pub fn is_folder(&self) -> bool {
+    self.stash_type == "Folder"
+  }
+
+  /// This tab followed by all of its children, depth first.
+  pub fn walk(&self) -> Vec<&StashTab> {
+    let mut tabs = vec![self];
+
+    for child in self.children.iter().flatten() {
+      tabs.extend(child.walk());
+    }
+
+    tabs
+  }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StashTabMetadata {
+  pub public: Option<bool>,
+  pub folder: Option<bool>,
+  pub colour: Option<String>,
+  pub map: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct StashesResponse {
+  stashes: Vec<StashTab>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct StashResponse {
+  stash: Option<StashTab>,
+}
+
+impl PoEApi {
+  pub async fn list_stashes(
+    &self,
+    token: &(impl TokenProvider + ?Sized),
+    league: &str,
+    realm: Option<Realm>,
+  ) -> Result<ApiResponse<Vec<StashTab>>> {
+    let realm = self.realm_segment(realm);
+    let request = self
+      .get(&format!("/stash{realm}/{league}"))?
+      .bearer_auth(token.bearer_token(self).await?);
+    let response = self.send_json::<StashesResponse>(request).await?;
+
+    Ok(response.map(|response| response.stashes))
+  }
+
+  pub async fn get_stash(
+    &self,
+    token: &(impl TokenProvider + ?Sized),
+    league: &str,
+    stash_id: &str,
+    substash_id: Option<&str>,
+    realm: Option<Realm>,
+  ) -> Result<ApiResponse<Option<StashTab>>> {
+    let realm = self.realm_segment(realm);
+    let endpoint = match substash_id {
+      Some(substash_id) => format!("/stash{realm}/{league}/{stash_id}/{substash_id}"),
+      None => format!("/stash{realm}/{league}/{stash_id}"),
+    };
+
+    let request = self
+      .get(&endpoint)?
+      .bearer_auth(token.bearer_token(self).await?);
+    let response = self.send_json::<StashResponse>(request).await?;
+
+    Ok(response.map(|response| response.stash))
+  }
+
+  pub async fn list_guild_stashes(
+    &self,
+    token: &(impl TokenProvider + ?Sized),
+    league: &str,
+    realm: Option<Realm>,
+  ) -> Result<ApiResponse<Vec<StashTab>>> {
+    let realm = self.realm_segment(realm);
+    let request = self
+      .get(&format!("/guild{realm}/stash/{league}"))?
+      .bearer_auth(token.bearer_token(self).await?);
+    let response = self.send_json::<StashesResponse>(request).await?;
+
+    Ok(response.map(|response| response.stashes))
+  }
+
+  pub async fn get_guild_stash(
+    &self,
+    token: &(impl TokenProvider + ?Sized),
+    league: &str,
+    stash_id: &str,
+    substash_id: Option<&str>,
+    realm: Option<Realm>,
+  ) -> Result<ApiResponse<Option<StashTab>>> {
+    let realm = self.realm_segment(realm);
+    let endpoint = match substash_id {
+      Some(substash_id) => format!("/guild{realm}/stash/{league}/{stash_id}/{substash_id}"),
+      None => format!("/guild{realm}/stash/{league}/{stash_id}"),
+    };
+
+    let request = self
+      .get(&endpoint)?
+      .bearer_auth(token.bearer_token(self).await?);
+    let response = self.send_json::<StashResponse>(request).await?;
+
+    Ok(response.map(|response| response.stash))
+  }
+}
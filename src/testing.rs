@@ -0,0 +1,633 @@
+//! An in-process stand-in for the PoE API and its OAuth endpoints, for tests that can't reach
+//! GGG's servers.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use oauth2::{PkceCodeChallenge, PkceCodeVerifier};
+use reqwest::Url;
+use serde_json::{json, Value};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::{PoEApiConfigBuilder, Result};
+
+pub const MOCK_CLIENT_ID: &str = "mock-client";
+pub const MOCK_CLIENT_SECRET: &str = "mock-secret";
+pub const MOCK_ACCESS_TOKEN: &str = "mock-access-token";
+pub const MOCK_REFRESH_TOKEN: &str = "mock-refresh-token";
+
+#[derive(Debug, Clone)]
+pub struct RecordedRequest {
+  pub method: String,
+  pub url: String,
+  pub authorization: Option<String>,
+  pub body: String,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct MockRateLimit {
+  max_hits: u32,
+  period: Duration,
+  restriction: Duration,
+}
+
+/// What an authorization code was issued for, checked again when it is exchanged.
+#[derive(Debug, Clone)]
+struct MockCode {
+  scope: String,
+  code_challenge: String,
+  redirect_uri: String,
+}
+
+#[derive(Debug)]
+struct MockState {
+  fixtures: HashMap<String, Value>,
+  errors: VecDeque<(u16, String, String)>,
+  tokens: HashSet<String>,
+  refresh_tokens: HashSet<String>,
+  codes: HashMap<String, MockCode>,
+  deny_authorization: bool,
+  token_lifetime: Duration,
+  rate_limit: MockRateLimit,
+  hits: HashMap<String, VecDeque<Instant>>,
+  restricted: HashMap<String, Instant>,
+  requests: Vec<RecordedRequest>,
+  issued: u32,
+}
+
+/// A local HTTP server emulating the OAuth and resource endpoints with fixture data.
+///
+/// The server runs on a background thread until dropped.
+pub struct MockServer {
+  addr: SocketAddr,
+  server: Arc<Server>,
+  state: Arc<Mutex<MockState>>,
+  thread: Option<JoinHandle<()>>,
+}
+
+impl MockServer {
+  pub fn start() -> Result<Self> {
+    let server = Arc::new(Server::http("127.0.0.1:0")?);
+    let addr = server
+      .server_addr()
+      .to_ip()
+      .ok_or_else(|| crate::Error::Custom("Mock server isn't bound to an ip address".into()))?;
+
+    let state = Arc::new(Mutex::new(MockState {
+      fixtures: default_fixtures(),
+      errors: VecDeque::new(),
+      tokens: HashSet::from([MOCK_ACCESS_TOKEN.to_string()]),
+      refresh_tokens: HashSet::from([MOCK_REFRESH_TOKEN.to_string()]),
+      codes: HashMap::new(),
+      deny_authorization: false,
+      token_lifetime: Duration::from_secs(36000),
+      rate_limit: MockRateLimit {
+        max_hits: 30,
+        period: Duration::from_secs(60),
+        restriction: Duration::from_secs(60),
+      },
+      hits: HashMap::new(),
+      restricted: HashMap::new(),
+      requests: Vec::new(),
+      issued: 0,
+    }));
+
+    let thread = {
+      let server = server.clone();
+      let state = state.clone();
+
+      std::thread::spawn(move || {
+        for request in server.incoming_requests() {
+          handle(&state, addr, request);
+        }
+      })
+    };
+
+    Ok(Self {
+      addr,
+      server,
+      state,
+      thread: Some(thread),
+    })
+  }
+
+  pub fn url(&self) -> String {
+    format!("http://{}", self.addr)
+  }
+
+  pub fn api_url(&self) -> String {
+    self.url()
+  }
+
+  pub fn auth_url(&self) -> String {
+    format!("{}/oauth/authorize", self.url())
+  }
+
+  pub fn token_url(&self) -> String {
+    format!("{}/oauth/token", self.url())
+  }
+
+  pub fn revoke_url(&self) -> String {
+    format!("{}/oauth/token/revoke", self.url())
+  }
+
+  /// Points a config at this server and fills in the mock client credentials.
+  pub fn configure(&self, builder: PoEApiConfigBuilder) -> PoEApiConfigBuilder {
+    builder
+      .client_id(MOCK_CLIENT_ID)
+      .client_secret(MOCK_CLIENT_SECRET)
+      .api_url(self.api_url())
+      .auth_url(self.auth_url())
+      .token_url(self.token_url())
+      .revoke_url(self.revoke_url())
+  }
+
+  /// Serves `body` for `GET <path>`, e.g. `set_fixture("/character", json!({ ... }))`. A `path`
+  /// with a query, e.g. `/league/Standard/ladder?limit=2&offset=0`, takes precedence over the
+  /// bare path for requests with exactly that query.
+  pub fn set_fixture(&self, path: &str, body: Value) {
+    self.set_fixture_for("GET", path, body)
+  }
+
+  pub fn set_fixture_for(&self, method: &str, path: &str, body: Value) {
+    let key = format!("{method} {path}");
+
+    self.state.lock().unwrap().fixtures.insert(key, body);
+  }
+
+  /// Makes the next resource request fail with `status` and a `PoEApiError` body.
+  pub fn fail_next(&self, status: u16, error: &str, error_description: &str) {
+    self.state.lock().unwrap().errors.push_back((
+      status,
+      error.to_string(),
+      error_description.to_string(),
+    ));
+  }
+
+  /// Limits every policy to `max_hits` requests per `period`, answering 429 for `restriction`
+  /// once exceeded.
+  pub fn set_rate_limit(&self, max_hits: u32, period: Duration, restriction: Duration) {
+    self.state.lock().unwrap().rate_limit = MockRateLimit {
+      max_hits,
+      period,
+      restriction,
+    };
+  }
+
+  /// Makes the authorize endpoint redirect with `error=access_denied`, as if the user clicked deny.
+  pub fn deny_authorization(&self, deny: bool) {
+    self.state.lock().unwrap().deny_authorization = deny;
+  }
+
+  pub fn set_token_lifetime(&self, lifetime: Duration) {
+    self.state.lock().unwrap().token_lifetime = lifetime;
+  }
+
+  pub fn add_token(&self, token: &str) {
+    self.state.lock().unwrap().tokens.insert(token.to_string());
+  }
+
+  pub fn revoke_all_tokens(&self) {
+    let mut state = self.state.lock().unwrap();
+
+    state.tokens.clear();
+    state.refresh_tokens.clear();
+  }
+
+  pub fn requests(&self) -> Vec<RecordedRequest> {
+    self.state.lock().unwrap().requests.clone()
+  }
+
+  /// Follows an authorize url like a browser would, on a background thread so it can be called
+  /// from the `get_token` callback.
+  pub fn visit(&self, url: Url) {
+    std::thread::spawn(move || {
+      let Ok(response) = http_get(&url) else {
+        return;
+      };
+
+      let location = response
+        .lines()
+        .take_while(|line| !line.is_empty())
+        .find_map(|line| {
+          let (name, value) = line.split_once(':')?;
+
+          name
+            .eq_ignore_ascii_case("location")
+            .then(|| value.trim().to_string())
+        });
+
+      if let Some(location) = location.and_then(|location| Url::parse(&location).ok()) {
+        let _ = http_get(&location);
+      }
+    });
+  }
+}
+
+impl Drop for MockServer {
+  fn drop(&mut self) {
+    self.server.unblock();
+
+    if let Some(thread) = self.thread.take() {
+      let _ = thread.join();
+    }
+  }
+}
+
+fn handle(state: &Mutex<MockState>, addr: SocketAddr, mut request: Request) {
+  let url = Url::parse(&format!("http://{addr}"))
+    .and_then(|base| base.join(request.url()))
+    .expect("tiny_http only accepts valid request urls");
+
+  let authorization = request
+    .headers()
+    .iter()
+    .find(|header| header.field.equiv("Authorization"))
+    .map(|header| header.value.to_string());
+
+  let mut body = String::new();
+  let _ = request.as_reader().read_to_string(&mut body);
+
+  state.lock().unwrap().requests.push(RecordedRequest {
+    method: request.method().to_string(),
+    url: url.to_string(),
+    authorization: authorization.clone(),
+    body: body.clone(),
+  });
+
+  let response = match (request.method(), url.path()) {
+    (Method::Get, "/oauth/authorize") => authorize(state, &url),
+    (Method::Post, "/oauth/token") => token(state, &body),
+    (Method::Post, "/oauth/token/revoke") => revoke(state, &body),
+    _ => resource(state, request.method(), &url, authorization.as_deref()),
+  };
+
+  let _ = request.respond(response);
+}
+
+fn authorize(state: &Mutex<MockState>, url: &Url) -> Response<std::io::Cursor<Vec<u8>>> {
+  let query = url.query_pairs().into_owned().collect::<HashMap<_, _>>();
+  let mut state = state.lock().unwrap();
+
+  if query.get("client_id").map(String::as_str) != Some(MOCK_CLIENT_ID) {
+    return error(400, "invalid_client", "unknown client");
+  }
+
+  let (Some(redirect_uri), Some(client_state)) = (query.get("redirect_uri"), query.get("state"))
+  else {
+    return error(400, "invalid_request", "missing redirect_uri or state");
+  };
+
+  // PKCE is mandatory, and only with S256.
+  let (Some(code_challenge), Some("S256")) = (
+    query.get("code_challenge"),
+    query.get("code_challenge_method").map(String::as_str),
+  ) else {
+    return error(
+      400,
+      "invalid_request",
+      "missing or unsupported code_challenge",
+    );
+  };
+
+  let Ok(mut location) = Url::parse(redirect_uri) else {
+    return error(400, "invalid_request", "invalid redirect_uri");
+  };
+
+  if state.deny_authorization {
+    location
+      .query_pairs_mut()
+      .append_pair("error", "access_denied")
+      .append_pair("error_description", "The user denied the request")
+      .append_pair("state", client_state);
+  } else {
+    state.issued += 1;
+
+    let code = format!("mock-code-{}", state.issued);
+
+    state.codes.insert(
+      code.clone(),
+      MockCode {
+        scope: query.get("scope").cloned().unwrap_or_default(),
+        code_challenge: code_challenge.clone(),
+        redirect_uri: redirect_uri.clone(),
+      },
+    );
+    location
+      .query_pairs_mut()
+      .append_pair("code", &code)
+      .append_pair("state", client_state);
+  }
+
+  Response::from_data(Vec::new())
+    .with_status_code(302)
+    .with_header(header("Location", location.as_str()))
+}
+
+fn token(state: &Mutex<MockState>, body: &str) -> Response<std::io::Cursor<Vec<u8>>> {
+  let form = url::form_urlencoded::parse(body.as_bytes())
+    .into_owned()
+    .collect::<HashMap<_, _>>();
+  let field = |name: &str| form.get(name).map(String::as_str);
+  let mut state = state.lock().unwrap();
+
+  if field("client_id") != Some(MOCK_CLIENT_ID) {
+    return error(401, "invalid_client", "unknown client");
+  }
+
+  let scope = match field("grant_type") {
+    // Codes are single use even when the exchange fails, like the real server.
+    Some("authorization_code") => field("code")
+      .and_then(|code| state.codes.remove(code))
+      .filter(|code| field("redirect_uri") == Some(code.redirect_uri.as_str()))
+      .filter(|code| {
+        field("code_verifier").is_some_and(|verifier| {
+          let verifier = PkceCodeVerifier::new(verifier.to_string());
+
+          PkceCodeChallenge::from_code_verifier_sha256(&verifier).as_str() == code.code_challenge
+        })
+      })
+      .map(|code| code.scope),
+    // Refresh tokens are single use, a new one comes with the response.
+    Some("refresh_token") => field("refresh_token")
+      .filter(|token| state.refresh_tokens.remove(*token))
+      .map(|_| String::new()),
+    Some("client_credentials") => (field("client_secret") == Some(MOCK_CLIENT_SECRET))
+      .then(|| field("scope").unwrap_or_default().to_string()),
+    _ => return error(400, "unsupported_grant_type", "unsupported grant type"),
+  };
+
+  let Some(scope) = scope else {
+    return error(400, "invalid_grant", "the provided grant is invalid");
+  };
+
+  state.issued += 1;
+
+  let access_token = format!("{MOCK_ACCESS_TOKEN}-{}", state.issued);
+  let refresh_token = format!("{MOCK_REFRESH_TOKEN}-{}", state.issued);
+
+  state.tokens.insert(access_token.clone());
+
+  let mut body = json!({
+    "access_token": access_token,
+    "expires_in": state.token_lifetime.as_secs(),
+    "token_type": "bearer",
+    "username": "MockAccount",
+    "sub": "c5b9c286-8d05-47af-be41-67ab10a8c53e",
+  });
+
+  if !scope.is_empty() {
+    body["scope"] = json!(scope);
+  }
+
+  if field("grant_type") != Some("client_credentials") {
+    state.refresh_tokens.insert(refresh_token.clone());
+    body["refresh_token"] = json!(refresh_token);
+  }
+
+  json_response(200, &body)
+}
+
+/// Unknown tokens are not an error, as in RFC 7009.
+fn revoke(state: &Mutex<MockState>, body: &str) -> Response<std::io::Cursor<Vec<u8>>> {
+  let form = url::form_urlencoded::parse(body.as_bytes())
+    .into_owned()
+    .collect::<HashMap<_, _>>();
+  let mut state = state.lock().unwrap();
+
+  if form.get("client_id").map(String::as_str) != Some(MOCK_CLIENT_ID) {
+    return error(401, "invalid_client", "unknown client");
+  }
+
+  let Some(token) = form.get("token") else {
+    return error(400, "invalid_request", "missing token");
+  };
+
+  state.tokens.remove(token);
+  state.refresh_tokens.remove(token);
+
+  Response::from_data(Vec::new()).with_status_code(200)
+}
+
+fn resource(
+  state: &Mutex<MockState>,
+  method: &Method,
+  url: &Url,
+  authorization: Option<&str>,
+) -> Response<std::io::Cursor<Vec<u8>>> {
+  let mut state = state.lock().unwrap();
+  let segment = url
+    .path_segments()
+    .and_then(|mut segments| segments.next())
+    .unwrap_or_default();
+  let policy = format!("{segment}-request-limit");
+  let now = Instant::now();
+  let limit = state.rate_limit;
+
+  let hits = state.hits.entry(policy.clone()).or_default();
+  hits.retain(|hit| *hit + limit.period > now);
+  hits.push_back(now);
+  let count = hits.len() as u32;
+
+  if count > limit.max_hits {
+    state
+      .restricted
+      .entry(policy.clone())
+      .or_insert(now + limit.restriction);
+  }
+
+  let restricted = state
+    .restricted
+    .get(&policy)
+    .map(|until| until.saturating_duration_since(now))
+    .filter(|left| !left.is_zero());
+
+  if restricted.is_none() {
+    state.restricted.remove(&policy);
+  }
+
+  let rate_limit_headers = [
+    header("X-Rate-Limit-Policy", &policy),
+    header("X-Rate-Limit-Rules", "Ip"),
+    header(
+      "X-Rate-Limit-Ip",
+      &format!(
+        "{}:{}:{}",
+        limit.max_hits,
+        limit.period.as_secs(),
+        limit.restriction.as_secs()
+      ),
+    ),
+    header(
+      "X-Rate-Limit-Ip-State",
+      &format!(
+        "{}:{}:{}",
+        count,
+        limit.period.as_secs(),
+        restricted.unwrap_or_default().as_secs()
+      ),
+    ),
+  ];
+
+  let response = if let Some(restricted) = restricted {
+    error(429, "rate_limit", "Rate limit exceeded").with_header(header(
+      "Retry-After",
+      &restricted.as_secs().max(1).to_string(),
+    ))
+  } else if let Some((status, error_name, description)) = state.errors.pop_front() {
+    error(status, &error_name, &description)
+  } else if !authorization
+    .and_then(|authorization| authorization.strip_prefix("Bearer "))
+    .is_some_and(|token| state.tokens.contains(token))
+  {
+    error(401, "invalid_token", "The access token provided is invalid")
+  } else {
+    let with_query = url.query().and_then(|query| {
+      state
+        .fixtures
+        .get(&format!("{method} {}?{query}", url.path()))
+    });
+
+    match with_query.or_else(|| state.fixtures.get(&format!("{method} {}", url.path()))) {
+      Some(body) => json_response(200, body),
+      None => error(404, "not_found", "Resource not found"),
+    }
+  };
+
+  rate_limit_headers
+    .into_iter()
+    .fold(response, |response, header| response.with_header(header))
+}
+
+fn json_response(status: u16, body: &Value) -> Response<std::io::Cursor<Vec<u8>>> {
+  Response::from_data(body.to_string().into_bytes())
+    .with_status_code(status)
+    .with_header(header("Content-Type", "application/json"))
+}
+
+fn error(status: u16, error: &str, description: &str) -> Response<std::io::Cursor<Vec<u8>>> {
+  json_response(
+    status,
+    &json!({ "error": error, "error_description": description }),
+  )
+}
+
+fn header(name: &str, value: &str) -> Header {
+  Header::from_bytes(name.as_bytes(), value.as_bytes()).expect("valid header")
+}
+
+fn http_get(url: &Url) -> std::io::Result<String> {
+  let addrs = url.socket_addrs(|| None)?;
+  let mut stream = TcpStream::connect(addrs.as_slice())?;
+  let target = match url.query() {
+    Some(query) => format!("{}?{query}", url.path()),
+    None => url.path().to_string(),
+  };
+
+  write!(
+    stream,
+    "GET {target} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n",
+    url.host_str().unwrap_or_default()
+  )?;
+
+  let mut response = String::new();
+  stream.read_to_string(&mut response)?;
+
+  Ok(response.replace("\r\n", "\n"))
+}
+
+fn default_fixtures() -> HashMap<String, Value> {
+  let league = json!({
+    "id": "Standard",
+    "realm": "pc",
+    "description": "The default game mode.",
+    "registerAt": "2013-01-23T21:00:00Z",
+    "url": "https://www.pathofexile.com/forum/view-thread/71278",
+    "startAt": "2013-01-23T21:00:00Z",
+    "endAt": null,
+    "delveEvent": true,
+    "rules": []
+  });
+
+  let item = json!({
+    "verified": false,
+    "w": 2,
+    "h": 3,
+    "icon": "https://web.poecdn.com/image/Art/2DItems/Armours/BodyArmours/BodyStr1.png",
+    "league": "Standard",
+    "id": "d9f7b2a0c4e3f1a6b5c8d7e9f0a1b2c3d4e5f6a7b8c9d0e1f2a3b4c5d6e7f8a9",
+    "name": "Mock Guard",
+    "typeLine": "Plate Vest",
+    "baseType": "Plate Vest",
+    "rarity": "Rare",
+    "identified": true,
+    "ilvl": 84,
+    "frameType": 2,
+    "sockets": [{ "group": 0, "attr": "S", "sColour": "R" }],
+    "properties": [{ "name": "Armour", "values": [["42", 0]], "displayMode": 0, "type": 16 }],
+    "explicitMods": ["+50 to maximum Life"],
+    "inventoryId": "BodyArmour",
+    "x": 0,
+    "y": 0
+  });
+
+  let character = json!({
+    "id": "4d6c7b4e2c1f0e9d8c7b6a5f4e3d2c1b0a9f8e7d6c5b4a3f2e1d0c9b8a7f6e5d",
+    "name": "MockCharacter",
+    "realm": "pc",
+    "class": "Marauder",
+    "league": "Standard",
+    "level": 90,
+    "experience": 1_200_000_000u64,
+    "equipment": [item],
+    "inventory": [],
+    "jewels": [],
+    "passives": { "hashes": [1, 2, 3], "hashes_ex": [], "mastery_effects": {}, "jewel_data": {} }
+  });
+
+  let stash = json!({
+    "id": "a1b2c3d4e5",
+    "name": "Loot",
+    "type": "PremiumStash",
+    "index": 0,
+    "metadata": { "colour": "ff0000" },
+    "items": [item]
+  });
+
+  HashMap::from([
+    (
+      "GET /profile".to_string(),
+      json!({ "uuid": "c5b9c286-8d05-47af-be41-67ab10a8c53e", "name": "MockAccount", "realm": "pc" }),
+    ),
+    ("GET /league".to_string(), json!({ "leagues": [league] })),
+    (
+      "GET /league/Standard".to_string(),
+      json!({ "league": league }),
+    ),
+    (
+      "GET /account/leagues".to_string(),
+      json!({ "leagues": [league] }),
+    ),
+    (
+      "GET /character".to_string(),
+      json!({ "characters": [character] }),
+    ),
+    (
+      "GET /character/MockCharacter".to_string(),
+      json!({ "character": character }),
+    ),
+    (
+      "GET /stash/Standard".to_string(),
+      json!({ "stashes": [stash] }),
+    ),
+    (
+      "GET /stash/Standard/a1b2c3d4e5".to_string(),
+      json!({ "stash": stash }),
+    ),
+    ("GET /item-filter".to_string(), json!({ "filters": [] })),
+  ])
+}
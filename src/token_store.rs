@@ -0,0 +1,160 @@
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use tokio::io::AsyncWriteExt;
+
+use crate::{Result, SessionToken};
+
+/// Persists [`SessionToken`]s keyed by client id and account so users don't have to log in again
+/// every time the application starts.
+#[async_trait::async_trait]
+pub trait TokenStore: Debug + Send + Sync {
+  async fn load(&self, client_id: &str, account: &str) -> Result<Option<SessionToken>>;
+
+  async fn save(&self, client_id: &str, account: &str, token: &SessionToken) -> Result<()>;
+
+  async fn delete(&self, client_id: &str, account: &str) -> Result<()>;
+}
+
+type Tokens = HashMap<String, HashMap<String, SessionToken>>;
+
+#[derive(Debug, Default)]
+pub struct MemoryTokenStore {
+  tokens: Mutex<Tokens>,
+}
+
+impl MemoryTokenStore {
+  pub fn new() -> Self {
+    Self::default()
+  }
+}
+
+#[async_trait::async_trait]
+impl TokenStore for MemoryTokenStore {
+  async fn load(&self, client_id: &str, account: &str) -> Result<Option<SessionToken>> {
+    let tokens = self.tokens.lock().unwrap();
+
+    Ok(
+      tokens
+        .get(client_id)
+        .and_then(|accounts| accounts.get(account))
+        .cloned(),
+    )
+  }
+
+  async fn save(&self, client_id: &str, account: &str, token: &SessionToken) -> Result<()> {
+    let mut tokens = self.tokens.lock().unwrap();
+
+    tokens
+      .entry(client_id.to_string())
+      .or_default()
+      .insert(account.to_string(), token.clone());
+
+    Ok(())
+  }
+
+  async fn delete(&self, client_id: &str, account: &str) -> Result<()> {
+    let mut tokens = self.tokens.lock().unwrap();
+
+    if let Some(accounts) = tokens.get_mut(client_id) {
+      accounts.remove(account);
+    }
+
+    Ok(())
+  }
+}
+
+/// Stores every token in a single JSON file, `{ "<client id>": { "<account>": <token> } }`.
+#[derive(Debug)]
+pub struct FileTokenStore {
+  path: PathBuf,
+  lock: tokio::sync::Mutex<()>,
+}
+
+impl FileTokenStore {
+  pub fn new(path: impl Into<PathBuf>) -> Self {
+    Self {
+      path: path.into(),
+      lock: tokio::sync::Mutex::new(()),
+    }
+  }
+
+  async fn read(&self) -> Result<Tokens> {
+    match tokio::fs::read(&self.path).await {
+      Ok(bytes) => serde_json::from_slice(&bytes).map_err(Into::into),
+      Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(Tokens::default()),
+      Err(error) => Err(error.into()),
+    }
+  }
+
+  /// Writes to a temporary file first so a crash can't leave a truncated file behind. On unix the
+  /// file is only readable by its owner, as it holds credentials.
+  async fn write(&self, tokens: &Tokens) -> Result<()> {
+    if let Some(parent) = self.path.parent() {
+      tokio::fs::create_dir_all(parent).await?;
+    }
+
+    let temp = self.path.with_extension("tmp");
+
+    // A leftover from a crash would keep its permissions, so start from a fresh file.
+    match tokio::fs::remove_file(&temp).await {
+      Err(error) if error.kind() != std::io::ErrorKind::NotFound => return Err(error.into()),
+      _ => {}
+    }
+
+    let mut options = tokio::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+
+    #[cfg(unix)]
+    options.mode(0o600);
+
+    let mut file = options.open(&temp).await?;
+
+    file.write_all(&serde_json::to_vec_pretty(tokens)?).await?;
+    file.sync_all().await?;
+    drop(file);
+
+    tokio::fs::rename(&temp, &self.path).await?;
+
+    Ok(())
+  }
+}
+
+#[async_trait::async_trait]
+impl TokenStore for FileTokenStore {
+  async fn load(&self, client_id: &str, account: &str) -> Result<Option<SessionToken>> {
+    let _lock = self.lock.lock().await;
+    let mut tokens = self.read().await?;
+
+    Ok(
+      tokens
+        .get_mut(client_id)
+        .and_then(|accounts| accounts.remove(account)),
+    )
+  }
+
+  async fn save(&self, client_id: &str, account: &str, token: &SessionToken) -> Result<()> {
+    let _lock = self.lock.lock().await;
+    let mut tokens = self.read().await?;
+
+    tokens
+      .entry(client_id.to_string())
+      .or_default()
+      .insert(account.to_string(), token.clone());
+
+    self.write(&tokens).await
+  }
+
+  async fn delete(&self, client_id: &str, account: &str) -> Result<()> {
+    let _lock = self.lock.lock().await;
+    let mut tokens = self.read().await?;
+
+    if let Some(accounts) = tokens.get_mut(client_id) {
+      accounts.remove(account);
+    }
+
+    self.write(&tokens).await
+  }
+}
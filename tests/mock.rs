@@ -0,0 +1,1005 @@
+use std::time::Duration;
+
+use chrono::Utc;
+use futures::{StreamExt, TryStreamExt};
+use oauth2::{PkceCodeChallenge, PkceCodeVerifier, TokenResponse};
+use poe_api::testing::{
+  MockServer, RecordedRequest, MOCK_ACCESS_TOKEN, MOCK_CLIENT_ID, MOCK_REFRESH_TOKEN,
+};
+use poe_api::{
+  Error, FrameType, ItemFilterChangesBuilder, ItemFilterType, LadderOptions, LadderOptionsBuilder,
+  ListLeaguesOptions, MemoryTokenStore, PoEApi, PoEApiAccountScope, PoEApiConfigBuilder,
+  PoEApiServiceScope, Realm, SessionToken, TokenStore,
+};
+use serde_json::json;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use url::Url;
+
+fn api(server: &MockServer) -> PoEApi {
+  let config = server
+    .configure(PoEApiConfigBuilder::default())
+    .version("0.1.0")
+    .contact_email("mock@example.com")
+    .redirect_addr("127.0.0.1:0")
+    .unwrap()
+    .build()
+    .unwrap();
+
+  PoEApi::new(config).unwrap()
+}
+
+#[tokio::test]
+async fn fetches_default_fixtures() {
+  let server = MockServer::start().unwrap();
+  let api = api(&server);
+
+  let profile = api.get_profile(MOCK_ACCESS_TOKEN).await.unwrap();
+  assert!(profile.rate_limit.is_some());
+
+  let leagues = api
+    .list_leagues(MOCK_ACCESS_TOKEN, &ListLeaguesOptions::default())
+    .await
+    .unwrap();
+  assert_eq!(leagues.len(), 1);
+  assert_eq!(leagues[0].id, "Standard");
+
+  let league = api
+    .get_league(MOCK_ACCESS_TOKEN, "Standard", None)
+    .await
+    .unwrap();
+  assert_eq!(league.data.unwrap().delve_event, Some(true));
+
+  let characters = api.list_characters(MOCK_ACCESS_TOKEN, None).await.unwrap();
+  assert_eq!(characters[0].name, "MockCharacter");
+
+  let character = api
+    .get_character(MOCK_ACCESS_TOKEN, "MockCharacter", None)
+    .await
+    .unwrap()
+    .into_inner()
+    .unwrap();
+  let equipment = character.equipment.unwrap();
+  assert_eq!(equipment[0].frame_type, Some(FrameType::Rare));
+  assert_eq!(equipment[0].properties[0].display(), "Armour: 42");
+
+  let stashes = api
+    .list_stashes(MOCK_ACCESS_TOKEN, "Standard", None)
+    .await
+    .unwrap();
+  assert_eq!(stashes[0].name, "Loot");
+
+  let stash = api
+    .get_stash(MOCK_ACCESS_TOKEN, "Standard", "a1b2c3d4e5", None, None)
+    .await
+    .unwrap()
+    .into_inner()
+    .unwrap();
+  assert_eq!(stash.items.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn maps_error_responses() {
+  let server = MockServer::start().unwrap();
+  let api = api(&server);
+
+  server.fail_next(503, "unavailable", "Down for maintenance");
+
+  let error = api.get_profile(MOCK_ACCESS_TOKEN).await.unwrap_err();
+  assert!(matches!(
+    error,
+    Error::PoEApiError { error, error_description }
+      if error == "unavailable" && error_description == "Down for maintenance"
+  ));
+
+  let error = api.get_profile("unknown-token").await.unwrap_err();
+  assert!(matches!(error, Error::PoEApiError { error, .. } if error == "invalid_token"));
+
+  assert!(api.get_profile(MOCK_ACCESS_TOKEN).await.is_ok());
+}
+
+fn count_requests(server: &MockServer, path: &str) -> usize {
+  server
+    .requests()
+    .iter()
+    .filter(|request| Url::parse(&request.url).unwrap().path() == path)
+    .count()
+}
+
+#[tokio::test]
+async fn retries_after_rate_limit() {
+  let server = MockServer::start().unwrap();
+  server.set_rate_limit(1, Duration::from_secs(2), Duration::from_secs(1));
+
+  // A second client doesn't know about the hit of the first one, so it runs into the 429.
+  let first = api(&server);
+  let second = api(&server);
+
+  first.get_profile(MOCK_ACCESS_TOKEN).await.unwrap();
+  second.get_profile(MOCK_ACCESS_TOKEN).await.unwrap();
+
+  assert_eq!(count_requests(&server, "/profile"), 3);
+}
+
+#[tokio::test]
+async fn surfaces_rate_limits_after_retrying() {
+  let server = MockServer::start().unwrap();
+  server.set_rate_limit(30, Duration::from_secs(60), Duration::from_secs(1));
+
+  let api = api(&server);
+
+  for _ in 0..=poe_api::MAX_RATE_LIMIT_RETRIES {
+    server.fail_next(429, "rate_limit", "Rate limit exceeded");
+  }
+
+  let error = api.get_profile(MOCK_ACCESS_TOKEN).await.unwrap_err();
+  assert!(matches!(
+    error,
+    Error::RateLimited { retry_after } if retry_after == Duration::from_secs(1)
+  ));
+
+  let expected = 1 + poe_api::MAX_RATE_LIMIT_RETRIES as usize;
+  assert_eq!(count_requests(&server, "/profile"), expected);
+}
+
+#[tokio::test]
+async fn gets_a_token_through_the_authorization_code_grant() {
+  let server = MockServer::start().unwrap();
+  let api = api(&server);
+
+  let token = api
+    .get_token([PoEApiAccountScope::Profile], |url| {
+      server.visit(url);
+      Ok::<_, Error>(())
+    })
+    .await
+    .unwrap();
+
+  assert!(api.get_profile(&token).await.is_ok());
+}
+
+#[tokio::test]
+async fn gets_a_service_token_through_the_client_credentials_grant() {
+  let server = MockServer::start().unwrap();
+  let api = api(&server);
+
+  let token = api
+    .get_service_token([PoEApiServiceScope::Leagues])
+    .await
+    .unwrap();
+
+  assert!(token.refresh_token().is_none());
+  assert_eq!(
+    token.scopes().unwrap()[0].as_str(),
+    PoEApiServiceScope::Leagues.to_string()
+  );
+  assert!(api
+    .list_leagues(&token, &ListLeaguesOptions::default())
+    .await
+    .is_ok());
+
+  // Public clients can't keep a secret, so they can't use the grant.
+  let config = PoEApiConfigBuilder::default()
+    .client_id(MOCK_CLIENT_ID)
+    .version("0.1.0")
+    .contact_email("mock@example.com")
+    .token_url(server.token_url())
+    .build()
+    .unwrap();
+  let error = PoEApi::new(config)
+    .unwrap()
+    .get_service_token([PoEApiServiceScope::Leagues])
+    .await
+    .unwrap_err();
+
+  assert!(matches!(error, Error::MissingClientSecret));
+}
+
+#[tokio::test]
+async fn reports_denied_authorization() {
+  let server = MockServer::start().unwrap();
+  let api = api(&server);
+
+  server.deny_authorization(true);
+
+  let error = api
+    .get_token([PoEApiAccountScope::Profile], |url| {
+      server.visit(url);
+      Ok::<_, Error>(())
+    })
+    .await
+    .unwrap_err();
+
+  assert!(matches!(
+    error,
+    Error::AuthorizationError { error, error_description }
+      if error == "access_denied" && error_description.is_some()
+  ));
+}
+
+#[tokio::test]
+async fn rejects_mismatched_code_exchanges() {
+  let server = MockServer::start().unwrap();
+  let client = reqwest::Client::builder()
+    .redirect(reqwest::redirect::Policy::none())
+    .build()
+    .unwrap();
+
+  let verifier = PkceCodeVerifier::new("a".repeat(43));
+  let challenge = PkceCodeChallenge::from_code_verifier_sha256(&verifier);
+  let redirect_uri = "http://127.0.0.1:1/callback";
+
+  for (code_verifier, exchange_redirect_uri) in [
+    ("b".repeat(43), redirect_uri),
+    ("a".repeat(43), "http://127.0.0.1:2/callback"),
+  ] {
+    let response = client
+      .get(server.auth_url())
+      .query(&[
+        ("client_id", MOCK_CLIENT_ID),
+        ("response_type", "code"),
+        ("redirect_uri", redirect_uri),
+        ("state", "state"),
+        ("code_challenge", challenge.as_str()),
+        ("code_challenge_method", "S256"),
+      ])
+      .send()
+      .await
+      .unwrap();
+
+    let location = Url::parse(response.headers()["location"].to_str().unwrap()).unwrap();
+    let code = location
+      .query_pairs()
+      .find(|(key, _)| key == "code")
+      .unwrap()
+      .1
+      .to_string();
+
+    let response = client
+      .post(server.token_url())
+      .form(&[
+        ("client_id", MOCK_CLIENT_ID),
+        ("grant_type", "authorization_code"),
+        ("code", &code),
+        ("code_verifier", &code_verifier),
+        ("redirect_uri", exchange_redirect_uri),
+      ])
+      .send()
+      .await
+      .unwrap();
+
+    assert_eq!(response.status(), 400);
+    let body = response.json::<serde_json::Value>().await.unwrap();
+    assert_eq!(body["error"], "invalid_grant");
+  }
+}
+
+fn stored_token(refresh_token: &str, expired: bool) -> SessionToken {
+  SessionToken {
+    access_token: MOCK_ACCESS_TOKEN.to_string(),
+    refresh_token: Some(refresh_token.to_string()),
+    expires_at: Some(match expired {
+      true => Utc::now(),
+      false => Utc::now() + chrono::Duration::hours(1),
+    }),
+    scopes: vec!["account:profile".to_string()],
+  }
+}
+
+async fn store_with(token: SessionToken) -> MemoryTokenStore {
+  let store = MemoryTokenStore::new();
+
+  store.save(MOCK_CLIENT_ID, "account", &token).await.unwrap();
+  store
+}
+
+fn no_authorization(_: Url) -> Result<(), Error> {
+  Err(Error::Custom("should not authorize again".into()))
+}
+
+#[tokio::test]
+async fn login_reuses_stored_tokens() {
+  let server = MockServer::start().unwrap();
+  let store = store_with(stored_token(MOCK_REFRESH_TOKEN, false)).await;
+  let api = api(&server).with_token_store(store);
+
+  let session = api
+    .login("account", [PoEApiAccountScope::Profile], no_authorization)
+    .await
+    .unwrap();
+
+  assert_eq!(session.token().await.access_token, MOCK_ACCESS_TOKEN);
+  assert!(api.get_profile(&session).await.is_ok());
+}
+
+#[tokio::test]
+async fn login_refreshes_expired_tokens() {
+  let server = MockServer::start().unwrap();
+  let store = store_with(stored_token(MOCK_REFRESH_TOKEN, true)).await;
+  let api = api(&server).with_token_store(store);
+
+  let session = api
+    .login("account", [PoEApiAccountScope::Profile], no_authorization)
+    .await
+    .unwrap();
+  let token = session.token().await;
+
+  assert_ne!(token.access_token, MOCK_ACCESS_TOKEN);
+  assert_eq!(token.scopes, ["account:profile"]);
+
+  // The refreshed token was saved, so the next start doesn't refresh again.
+  let stored = api
+    .token_store()
+    .unwrap()
+    .load(MOCK_CLIENT_ID, "account")
+    .await
+    .unwrap()
+    .unwrap();
+  assert_eq!(stored.access_token, token.access_token);
+}
+
+#[tokio::test]
+async fn login_authorizes_again_when_the_refresh_token_is_rejected() {
+  let server = MockServer::start().unwrap();
+  let store = store_with(stored_token("revoked-refresh-token", true)).await;
+  let api = api(&server).with_token_store(store);
+
+  let mut authorized = false;
+
+  api
+    .login("account", [PoEApiAccountScope::Profile], |url| {
+      authorized = true;
+      server.visit(url);
+      Ok::<_, Error>(())
+    })
+    .await
+    .unwrap();
+
+  assert!(authorized);
+}
+
+#[tokio::test]
+async fn login_reports_failed_refreshes() {
+  let server = MockServer::start().unwrap();
+  let store = store_with(stored_token(MOCK_REFRESH_TOKEN, true)).await;
+
+  // Nothing listens on port 1, so refreshing fails without the grant being rejected.
+  let config = server
+    .configure(PoEApiConfigBuilder::default())
+    .version("0.1.0")
+    .contact_email("mock@example.com")
+    .token_url("http://127.0.0.1:1/oauth/token")
+    .build()
+    .unwrap();
+  let api = PoEApi::new(config).unwrap().with_token_store(store);
+
+  let error = api
+    .login("account", [PoEApiAccountScope::Profile], no_authorization)
+    .await
+    .unwrap_err();
+
+  assert!(matches!(error, Error::RequestTokenError(_)));
+  assert!(!error.is_invalid_grant());
+}
+
+#[tokio::test]
+async fn cancels_only_the_given_authorization() {
+  let server = MockServer::start().unwrap();
+  let api = api(&server);
+  let (cancel, cancelled) = tokio::sync::oneshot::channel::<()>();
+
+  // Cancelling before the flow starts waiting must not get lost.
+  cancel.send(()).unwrap();
+
+  let (first, second) = tokio::join!(
+    api.get_token_until(
+      [PoEApiAccountScope::Profile],
+      |_| Ok::<_, Error>(()),
+      async {
+        let _ = cancelled.await;
+      }
+    ),
+    api.get_token([PoEApiAccountScope::Profile], |url| {
+      server.visit(url);
+      Ok::<_, Error>(())
+    }),
+  );
+
+  assert!(matches!(first, Err(Error::AuthorizationCancelled)));
+  assert!(second.is_ok());
+}
+
+#[tokio::test]
+async fn times_out_waiting_for_authorization() {
+  let server = MockServer::start().unwrap();
+  let config = server
+    .configure(PoEApiConfigBuilder::default())
+    .version("0.1.0")
+    .contact_email("mock@example.com")
+    .redirect_addr("127.0.0.1:0")
+    .unwrap()
+    .authorization_timeout(Duration::from_millis(100))
+    .build()
+    .unwrap();
+  let api = PoEApi::new(config).unwrap();
+
+  let error = api
+    .get_token([PoEApiAccountScope::Profile], |_| Ok::<_, Error>(()))
+    .await
+    .unwrap_err();
+
+  assert!(matches!(error, Error::AuthorizationTimedOut));
+}
+
+#[tokio::test]
+async fn derives_the_redirect_port_from_the_listener() {
+  let server = MockServer::start().unwrap();
+  let builder = || {
+    server
+      .configure(PoEApiConfigBuilder::default())
+      .version("0.1.0")
+      .contact_email("mock@example.com")
+  };
+
+  let api = PoEApi::new(
+    builder()
+      .redirect_url("http://127.0.0.1:0/callback")
+      .unwrap()
+      .build()
+      .unwrap(),
+  )
+  .unwrap();
+
+  let mut redirect_uri = None;
+
+  api
+    .get_token([PoEApiAccountScope::Profile], |url| {
+      redirect_uri = url
+        .query_pairs()
+        .find(|(key, _)| key == "redirect_uri")
+        .and_then(|(_, value)| Url::parse(&value).ok());
+      server.visit(url);
+      Ok::<_, Error>(())
+    })
+    .await
+    .unwrap();
+
+  let redirect_uri = redirect_uri.unwrap();
+  assert_ne!(redirect_uri.port(), Some(0));
+  assert_eq!(redirect_uri.path(), "/callback");
+
+  // A fixed port in the url can't match a port picked by the OS.
+  let api = PoEApi::new(
+    builder()
+      .redirect_url("http://127.0.0.1:8088")
+      .unwrap()
+      .redirect_addr("127.0.0.1:0")
+      .unwrap()
+      .build()
+      .unwrap(),
+  )
+  .unwrap();
+
+  let error = api
+    .get_token([PoEApiAccountScope::Profile], |_| Ok::<_, Error>(()))
+    .await
+    .unwrap_err();
+
+  assert!(matches!(error, Error::RedirectPortMismatch { port, .. } if port != 8088));
+}
+
+fn ladder_page(ranks: std::ops::Range<u32>, total: u32) -> serde_json::Value {
+  let entries = ranks
+    .map(|rank| {
+      json!({
+        "rank": rank,
+        "character": {
+          "id": format!("character-{rank}"),
+          "name": format!("Character{rank}"),
+          "level": 100,
+          "class": "Marauder",
+        },
+      })
+    })
+    .collect::<Vec<_>>();
+
+  json!({
+    "league": { "id": "Standard" },
+    "ladder": { "total": total, "entries": entries },
+  })
+}
+
+fn ladder_options(limit: u32) -> LadderOptions {
+  LadderOptionsBuilder::default()
+    .limit(limit)
+    .build()
+    .unwrap()
+}
+
+#[tokio::test]
+async fn walks_every_ladder_page() {
+  let server = MockServer::start().unwrap();
+  let api = api(&server);
+
+  server.set_fixture(
+    "/league/Standard/ladder?limit=2&offset=0",
+    ladder_page(1..3, 5),
+  );
+  server.set_fixture(
+    "/league/Standard/ladder?limit=2&offset=2",
+    ladder_page(3..5, 5),
+  );
+  server.set_fixture(
+    "/league/Standard/ladder?limit=2&offset=4",
+    ladder_page(5..6, 5),
+  );
+
+  let ranks = api
+    .league_ladder_stream(MOCK_ACCESS_TOKEN, "Standard", ladder_options(2))
+    .map_ok(|entry| entry.rank)
+    .try_collect::<Vec<_>>()
+    .await
+    .unwrap();
+
+  assert_eq!(ranks, [1, 2, 3, 4, 5]);
+  assert_eq!(count_requests(&server, "/league/Standard/ladder"), 3);
+}
+
+#[tokio::test]
+async fn stops_at_the_ladder_total() {
+  let server = MockServer::start().unwrap();
+  let api = api(&server);
+
+  // Any page past the total would be served the bare path fixture.
+  server.set_fixture("/league/Standard/ladder", ladder_page(0..0, 0));
+  server.set_fixture(
+    "/league/Standard/ladder?limit=2&offset=0",
+    ladder_page(1..3, 4),
+  );
+  server.set_fixture(
+    "/league/Standard/ladder?limit=2&offset=2",
+    ladder_page(3..5, 4),
+  );
+
+  let entries = api
+    .league_ladder_stream(MOCK_ACCESS_TOKEN, "Standard", ladder_options(2))
+    .try_collect::<Vec<_>>()
+    .await
+    .unwrap();
+
+  assert_eq!(entries.len(), 4);
+  assert_eq!(count_requests(&server, "/league/Standard/ladder"), 2);
+}
+
+#[tokio::test]
+async fn stops_at_an_empty_ladder_page() {
+  let server = MockServer::start().unwrap();
+  let api = api(&server);
+
+  // The total can be stale, an empty page ends the ladder regardless.
+  server.set_fixture(
+    "/league/Standard/ladder?limit=2&offset=0",
+    ladder_page(1..3, 10),
+  );
+  server.set_fixture(
+    "/league/Standard/ladder?limit=2&offset=2",
+    ladder_page(0..0, 10),
+  );
+
+  let entries = api
+    .league_ladder_stream(MOCK_ACCESS_TOKEN, "Standard", ladder_options(2))
+    .try_collect::<Vec<_>>()
+    .await
+    .unwrap();
+
+  assert_eq!(entries.len(), 2);
+  assert_eq!(count_requests(&server, "/league/Standard/ladder"), 2);
+}
+
+fn public_stashes(next_change_id: &str, stash_ids: &[&str]) -> serde_json::Value {
+  let stashes = stash_ids
+    .iter()
+    .map(|id| json!({ "id": id, "public": true, "stashType": "PremiumStash", "items": [] }))
+    .collect::<Vec<_>>();
+
+  json!({ "next_change_id": next_change_id, "stashes": stashes })
+}
+
+#[tokio::test]
+async fn follows_the_public_stash_change_ids() {
+  let server = MockServer::start().unwrap();
+  let api = api(&server);
+
+  server.set_fixture("/public-stash-tabs", public_stashes("1-1", &["first"]));
+  server.set_fixture(
+    "/public-stash-tabs?id=1-1",
+    public_stashes("2-2", &["second"]),
+  );
+
+  let mut stream = api.public_stash_stream(MOCK_ACCESS_TOKEN, None, None);
+
+  let first = stream.next().await.unwrap().unwrap();
+  assert_eq!(first.stashes[0].id, "first");
+  assert_eq!(stream.next_change_id(), Some("1-1"));
+
+  let second = stream.next().await.unwrap().unwrap();
+  assert_eq!(second.stashes[0].id, "second");
+  assert_eq!(stream.next_change_id(), Some("2-2"));
+}
+
+#[tokio::test]
+async fn skips_empty_public_stash_batches() {
+  let server = MockServer::start().unwrap();
+  let api = api(&server);
+
+  server.set_fixture("/public-stash-tabs?id=1-1", public_stashes("2-2", &[]));
+  server.set_fixture(
+    "/public-stash-tabs?id=2-2",
+    public_stashes("3-3", &["found"]),
+  );
+
+  let mut stream = api
+    .public_stash_stream(MOCK_ACCESS_TOKEN, Some("1-1".to_string()), None)
+    .with_poll_interval(Duration::from_millis(10));
+
+  let batch = stream.next().await.unwrap().unwrap();
+  assert_eq!(batch.stashes[0].id, "found");
+  assert_eq!(stream.next_change_id(), Some("3-3"));
+  assert_eq!(count_requests(&server, "/public-stash-tabs"), 2);
+}
+
+#[tokio::test]
+async fn retries_the_same_change_id_after_an_error() {
+  let server = MockServer::start().unwrap();
+  let api = api(&server);
+
+  server.set_fixture(
+    "/public-stash-tabs?id=1-1",
+    public_stashes("2-2", &["found"]),
+  );
+  server.fail_next(503, "unavailable", "Service unavailable");
+
+  let mut stream = api
+    .public_stash_stream(MOCK_ACCESS_TOKEN, Some("1-1".to_string()), None)
+    .with_poll_interval(Duration::from_millis(10));
+
+  let error = stream.next().await.unwrap().unwrap_err();
+  assert!(matches!(error, Error::PoEApiError { .. }));
+  assert_eq!(stream.next_change_id(), Some("1-1"));
+
+  let batch = stream.next().await.unwrap().unwrap();
+  assert_eq!(batch.stashes[0].id, "found");
+  assert_eq!(stream.next_change_id(), Some("2-2"));
+}
+
+fn item_filter(id: &str, filter: Option<&str>) -> serde_json::Value {
+  json!({
+    "id": id,
+    "filter_name": "Mock Filter",
+    "realm": "pc",
+    "description": "",
+    "version": "1",
+    "type": "Normal",
+    "public": false,
+    "filter": filter,
+  })
+}
+
+fn last_request(server: &MockServer) -> RecordedRequest {
+  server.requests().pop().unwrap()
+}
+
+#[tokio::test]
+async fn manages_item_filters() {
+  let server = MockServer::start().unwrap();
+  let api = api(&server);
+
+  server.set_fixture(
+    "/item-filter",
+    json!({ "filters": [item_filter("a1", None)] }),
+  );
+  server.set_fixture(
+    "/item-filter/a1",
+    json!({ "filter": item_filter("a1", Some("Show")) }),
+  );
+  server.set_fixture_for(
+    "POST",
+    "/item-filter",
+    json!({ "filter": item_filter("b2", Some("Hide")) }),
+  );
+  server.set_fixture_for(
+    "POST",
+    "/item-filter/a1",
+    json!({ "filter": item_filter("a1", Some("Hide")) }),
+  );
+
+  let filters = api.list_item_filters(MOCK_ACCESS_TOKEN).await.unwrap();
+  assert_eq!(filters[0].filter_type, ItemFilterType::Normal);
+  assert!(filters[0].filter.is_none());
+
+  let filter = api.get_item_filter(MOCK_ACCESS_TOKEN, "a1").await.unwrap();
+  assert_eq!(filter.filter.as_deref(), Some("Show"));
+
+  let changes = ItemFilterChangesBuilder::default()
+    .filter_name("Mock Filter")
+    .filter("Hide")
+    .build()
+    .unwrap();
+  let created = api
+    .create_item_filter(MOCK_ACCESS_TOKEN, &changes, true)
+    .await
+    .unwrap();
+  assert_eq!(created.id, "b2");
+
+  let request = last_request(&server);
+  let body = serde_json::from_str::<serde_json::Value>(&request.body).unwrap();
+  assert_eq!(request.method, "POST");
+  assert!(request.url.ends_with("/item-filter?validate=true"));
+  assert_eq!(
+    body,
+    json!({ "filter_name": "Mock Filter", "filter": "Hide" })
+  );
+
+  // Only the given changes are sent, and no validation unless asked for.
+  let changes = ItemFilterChangesBuilder::default()
+    .filter("Hide")
+    .build()
+    .unwrap();
+  let updated = api
+    .update_item_filter(MOCK_ACCESS_TOKEN, "a1", &changes, false)
+    .await
+    .unwrap();
+  assert_eq!(updated.filter.as_deref(), Some("Hide"));
+
+  let request = last_request(&server);
+  let body = serde_json::from_str::<serde_json::Value>(&request.body).unwrap();
+  assert!(request.url.ends_with("/item-filter/a1"));
+  assert_eq!(body, json!({ "filter": "Hide" }));
+}
+
+#[tokio::test]
+async fn takes_the_realm_of_account_leagues_in_the_path() {
+  let server = MockServer::start().unwrap();
+  let api = api(&server);
+
+  server.set_fixture("/account/leagues/xbox", json!({ "leagues": [] }));
+  server.set_fixture(
+    "/league-account/xbox/Standard",
+    json!({ "league_account": { "atlas_passives": { "hashes": [1, 2] } } }),
+  );
+
+  let leagues = api
+    .get_account_leagues(MOCK_ACCESS_TOKEN, None)
+    .await
+    .unwrap();
+  assert_eq!(leagues[0].id, "Standard");
+
+  let leagues = api
+    .get_account_leagues(MOCK_ACCESS_TOKEN, Some(Realm::Xbox))
+    .await
+    .unwrap();
+  assert!(leagues.is_empty());
+
+  let account = api
+    .get_league_account(MOCK_ACCESS_TOKEN, "Standard", Some(Realm::Xbox))
+    .await
+    .unwrap();
+  assert_eq!(account.atlas_passives.as_ref().unwrap().hashes, [1, 2]);
+  assert!(last_request(&server)
+    .url
+    .ends_with("/league-account/xbox/Standard"));
+}
+
+fn guild_stash(id: &str, name: &str) -> serde_json::Value {
+  json!({
+    "id": id,
+    "name": name,
+    "type": "GuildStash",
+    "metadata": { "public": false },
+  })
+}
+
+#[tokio::test]
+async fn fetches_guild_stashes() {
+  let server = MockServer::start().unwrap();
+  let api = api(&server);
+
+  server.set_fixture(
+    "/guild/stash/Standard",
+    json!({ "stashes": [guild_stash("g1", "Guild")] }),
+  );
+  server.set_fixture(
+    "/guild/stash/Standard/g1/g2",
+    json!({ "stash": guild_stash("g2", "Guild Substash") }),
+  );
+
+  let stashes = api
+    .list_guild_stashes(MOCK_ACCESS_TOKEN, "Standard", None)
+    .await
+    .unwrap();
+  assert_eq!(stashes[0].id, "g1");
+
+  let stash = api
+    .get_guild_stash(MOCK_ACCESS_TOKEN, "Standard", "g1", Some("g2"), None)
+    .await
+    .unwrap()
+    .into_inner()
+    .unwrap();
+  assert_eq!(stash.name, "Guild Substash");
+
+  // The account stash fixtures aren't served for guild stashes.
+  let error = api
+    .get_guild_stash(MOCK_ACCESS_TOKEN, "Standard", "a1b2c3d4e5", None, None)
+    .await
+    .unwrap_err();
+  assert!(matches!(error, Error::PoEApiError { .. }));
+}
+
+#[tokio::test]
+async fn routes_requests_to_the_default_realm() {
+  let server = MockServer::start().unwrap();
+  let config = server
+    .configure(PoEApiConfigBuilder::default())
+    .version("0.1.0")
+    .contact_email("mock@example.com")
+    .realm(Realm::Xbox)
+    .build()
+    .unwrap();
+  let api = PoEApi::new(config).unwrap();
+
+  server.set_fixture("/character/xbox", json!({ "characters": [] }));
+
+  let characters = api.list_characters(MOCK_ACCESS_TOKEN, None).await.unwrap();
+  assert!(characters.is_empty());
+
+  api
+    .get_league(MOCK_ACCESS_TOKEN, "Standard", None)
+    .await
+    .unwrap();
+  assert!(last_request(&server)
+    .url
+    .ends_with("/league/Standard?realm=xbox"));
+
+  // A realm given per call wins over the default one.
+  let characters = api
+    .list_characters(MOCK_ACCESS_TOKEN, Some(Realm::Pc))
+    .await
+    .unwrap();
+  assert_eq!(characters[0].name, "MockCharacter");
+
+  api
+    .get_league(MOCK_ACCESS_TOKEN, "Standard", Some(Realm::Pc))
+    .await
+    .unwrap();
+  assert!(last_request(&server)
+    .url
+    .ends_with("/league/Standard?realm=pc"));
+}
+
+/// Sends a bare `GET` to the callback listener and returns the status line of the response.
+async fn callback_status(redirect_url: &Url, target: &str) -> String {
+  let addrs = redirect_url.socket_addrs(|| None).unwrap();
+  let mut stream = tokio::net::TcpStream::connect(addrs.as_slice())
+    .await
+    .unwrap();
+
+  stream
+    .write_all(format!("GET {target} HTTP/1.1\r\nConnection: close\r\n\r\n").as_bytes())
+    .await
+    .unwrap();
+
+  let mut response = String::new();
+  stream.read_to_string(&mut response).await.unwrap();
+
+  response.lines().next().unwrap_or_default().to_string()
+}
+
+#[tokio::test]
+async fn ignores_forged_authorization_callbacks() {
+  let server = MockServer::start().unwrap();
+  let api = api(&server);
+  let (authorize, authorize_url) = tokio::sync::oneshot::channel::<Url>();
+
+  let (token, _) = tokio::join!(
+    api.get_token([PoEApiAccountScope::Profile], |url| {
+      authorize.send(url).unwrap();
+      Ok::<_, Error>(())
+    }),
+    async {
+      let url = authorize_url.await.unwrap();
+      let redirect_url = url
+        .query_pairs()
+        .find(|(key, _)| key == "redirect_uri")
+        .and_then(|(_, value)| Url::parse(&value).ok())
+        .unwrap();
+
+      // Without the state only known to the provider, none of these may end the flow.
+      for target in [
+        "/?code=forged",
+        "/?code=forged&state=forged",
+        "/?error=access_denied&state=forged",
+      ] {
+        assert_eq!(
+          callback_status(&redirect_url, target).await,
+          "HTTP/1.1 400 Bad Request"
+        );
+      }
+
+      server.visit(url);
+    },
+  );
+
+  assert!(api.get_profile(&token.unwrap()).await.is_ok());
+}
+
+#[tokio::test]
+async fn logout_revokes_and_forgets_the_tokens() {
+  let server = MockServer::start().unwrap();
+  let api = api(&server).with_token_store(MemoryTokenStore::new());
+
+  let session = api
+    .login("account", [PoEApiAccountScope::Profile], |url| {
+      server.visit(url);
+      Ok::<_, Error>(())
+    })
+    .await
+    .unwrap();
+  let token = session.token().await;
+  let store = api.token_store().unwrap();
+
+  assert!(store
+    .load(MOCK_CLIENT_ID, "account")
+    .await
+    .unwrap()
+    .is_some());
+
+  api.logout(&session).await.unwrap();
+
+  let error = api
+    .get_profile(token.access_token.as_str())
+    .await
+    .unwrap_err();
+  assert!(matches!(error, Error::PoEApiError { error, .. } if error == "invalid_token"));
+
+  let error = api
+    .refresh_token(token.refresh_token.as_deref().unwrap())
+    .await
+    .unwrap_err();
+  assert!(error.is_invalid_grant());
+
+  assert!(store
+    .load(MOCK_CLIENT_ID, "account")
+    .await
+    .unwrap()
+    .is_none());
+}
+
+#[tokio::test]
+async fn logout_forgets_the_tokens_when_revoking_fails() {
+  let server = MockServer::start().unwrap();
+  let store = store_with(stored_token(MOCK_REFRESH_TOKEN, false)).await;
+
+  // Nothing listens on port 1, so revoking fails.
+  let config = server
+    .configure(PoEApiConfigBuilder::default())
+    .version("0.1.0")
+    .contact_email("mock@example.com")
+    .revoke_url("http://127.0.0.1:1/oauth/token/revoke")
+    .build()
+    .unwrap();
+  let api = PoEApi::new(config).unwrap().with_token_store(store);
+
+  let session = api
+    .login("account", [PoEApiAccountScope::Profile], no_authorization)
+    .await
+    .unwrap();
+
+  assert!(api.logout(&session).await.is_err());
+
+  let stored = api
+    .token_store()
+    .unwrap()
+    .load(MOCK_CLIENT_ID, "account")
+    .await
+    .unwrap();
+  assert!(stored.is_none());
+}
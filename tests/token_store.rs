@@ -0,0 +1,37 @@
+use poe_api::{FileTokenStore, SessionToken, TokenStore};
+
+fn token() -> SessionToken {
+  SessionToken {
+    access_token: "access".to_string(),
+    refresh_token: Some("refresh".to_string()),
+    expires_at: None,
+    scopes: vec!["account:profile".to_string()],
+  }
+}
+
+#[tokio::test]
+async fn file_store_round_trips_tokens() {
+  let dir = std::env::temp_dir().join(format!("poe_api-{}", std::process::id()));
+  let path = dir.join("tokens.json");
+  let store = FileTokenStore::new(&path);
+
+  store.save("client", "account", &token()).await.unwrap();
+
+  let loaded = store.load("client", "account").await.unwrap().unwrap();
+  assert_eq!(loaded.access_token, "access");
+  assert_eq!(loaded.scopes, token().scopes);
+  assert!(store.load("client", "other").await.unwrap().is_none());
+
+  #[cfg(unix)]
+  {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o600);
+  }
+
+  store.delete("client", "account").await.unwrap();
+  assert!(store.load("client", "account").await.unwrap().is_none());
+
+  std::fs::remove_dir_all(dir).unwrap();
+}